@@ -2,6 +2,8 @@ from __future__ import annotations
 
 from typing import Iterable, Sequence
 
+import numpy as np
+
 try:
     from qce_kernels_py import taa_reproject_py as _taa_reproject
 except ImportError as exc:  # pragma: no cover - informative failure during setup
@@ -11,22 +13,23 @@ except ImportError as exc:  # pragma: no cover - informative failure during setu
     ) from exc
 
 
-def _as_float_list(buffer: Sequence[float] | Iterable[float]) -> list[float]:
-    if isinstance(buffer, list):
-        return buffer
-    return [float(value) for value in buffer]
+def _as_float32_array(buffer: Sequence[float] | Iterable[float] | np.ndarray) -> np.ndarray:
+    """Return a contiguous float32 view, copying only when the input needs it."""
+    if not isinstance(buffer, (np.ndarray, list, tuple)):
+        buffer = list(buffer)
+    return np.ascontiguousarray(buffer, dtype=np.float32).reshape(-1)
 
 
 def denoise_frame(
-    curr: Sequence[float] | Iterable[float],
-    prev: Sequence[float] | Iterable[float],
-    motion: Sequence[float] | Iterable[float],
+    curr: Sequence[float] | Iterable[float] | np.ndarray,
+    prev: Sequence[float] | Iterable[float] | np.ndarray,
+    motion: Sequence[float] | Iterable[float] | np.ndarray,
     width: int,
     height: int,
     blend: float = 0.9,
 ) -> list[float]:
     """Blend the current frame with history using the Rust TAA kernel."""
-    curr_buf = _as_float_list(curr)
-    prev_buf = _as_float_list(prev)
-    motion_buf = _as_float_list(motion)
-    return _taa_reproject(curr_buf, prev_buf, motion_buf, width, height, blend)
+    curr_buf = _as_float32_array(curr)
+    prev_buf = _as_float32_array(prev)
+    motion_buf = _as_float32_array(motion)
+    return _taa_reproject(curr_buf, prev_buf, motion_buf, width, height, blend).tolist()
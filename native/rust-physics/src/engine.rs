@@ -0,0 +1,1000 @@
+//! Platform-independent force-directed layout: node/edge types, the
+//! Barnes-Hut octree and the integrator. The wasm and Python bindings wrap
+//! [`Engine`].
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// Node representation with position and velocity
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Node {
+    pub id: String,
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+    pub vx: f64,
+    pub vy: f64,
+    pub vz: f64,
+    pub mass: f64,
+    /// Collision radius used by [`Engine::overlapping_pairs`]; nodes without
+    /// one fall back to the default radius passed there.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub radius: Option<f64>,
+}
+
+// Edge representation
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Edge {
+    pub source: String,
+    pub target: String,
+    pub weight: f64,
+}
+
+/// One segment of an annealing schedule: the layout runs at `temperature`
+/// for `ticks` ticks. See [`Engine::set_schedule`].
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SchedulePhase {
+    pub temperature: f64,
+    pub ticks: u32,
+}
+
+/// How edge weights are rescaled before they scale the spring forces. The
+/// edges keep their original weights; only the forces see the rescaled ones.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum WeightNormalization {
+    /// Use the weights as given.
+    #[default]
+    None,
+    /// Divide every weight by the largest, so they lie in `[0, 1]` for
+    /// non-negative input.
+    MaxScale,
+    /// `ln(1 + w)`, compressing large weights. Negative weights count as `0`.
+    Log,
+}
+
+impl WeightNormalization {
+    fn apply(self, edges: &[Edge]) -> Vec<f64> {
+        match self {
+            WeightNormalization::None => edges.iter().map(|e| e.weight).collect(),
+            WeightNormalization::MaxScale => {
+                let max = edges.iter().map(|e| e.weight).fold(0.0, f64::max);
+                let scale = if max > 0.0 { 1.0 / max } else { 1.0 };
+                edges.iter().map(|e| e.weight * scale).collect()
+            }
+            WeightNormalization::Log => edges.iter().map(|e| e.weight.max(0.0).ln_1p()).collect(),
+        }
+    }
+}
+
+/// Node attribute that orders the rings of a [`RadialLayout`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RankAttribute {
+    /// Number of edges touching the node.
+    Degree,
+    Mass,
+}
+
+/// Concentric-ring layout: nodes sharing a value of `rank_by` are pulled onto
+/// the same ring around the centre of mass. The largest value gets rank 0 at
+/// the centre, the next `ring_spacing` out, and so on; `strength` scales the
+/// radial spring. Edges and repulsion keep acting, which spreads the nodes
+/// along their rings.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RadialLayout {
+    pub rank_by: RankAttribute,
+    pub ring_spacing: f64,
+    pub strength: f64,
+}
+
+impl Default for RadialLayout {
+    fn default() -> Self {
+        RadialLayout {
+            rank_by: RankAttribute::Degree,
+            ring_spacing: 100.0,
+            strength: 1.0,
+        }
+    }
+}
+
+/// Coordinate axis, e.g. for [`Engine::set_directional_force`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Axis {
+    X,
+    Y,
+    Z,
+}
+
+impl Axis {
+    fn of(self, v: (f64, f64, f64)) -> f64 {
+        match self {
+            Axis::X => v.0,
+            Axis::Y => v.1,
+            Axis::Z => v.2,
+        }
+    }
+
+    fn component(self, v: &mut (f64, f64, f64)) -> &mut f64 {
+        match self {
+            Axis::X => &mut v.0,
+            Axis::Y => &mut v.1,
+            Axis::Z => &mut v.2,
+        }
+    }
+}
+
+/// Shape of the Barnes-Hut octree, see [`Engine::tree_stats`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TreeStats {
+    /// Cells in the tree, empty ones included.
+    pub node_count: usize,
+    /// Depth of the deepest cell; the root is at depth `0`.
+    pub max_depth: usize,
+    /// Mean number of graph nodes per non-empty leaf cell.
+    pub average_leaf_occupancy: f64,
+}
+
+/// Depth below which octree cells stop subdividing; the leaves there may hold
+/// several (coincident) nodes.
+const MAX_TREE_DEPTH: usize = 32;
+
+// Barnes-Hut quadtree node
+struct QuadTreeNode {
+    bounds: BoundingBox,
+    center_of_mass: (f64, f64, f64),
+    total_mass: f64,
+    children: Option<Box<[QuadTreeNode; 8]>>,
+    node_ids: Vec<usize>,
+}
+
+#[derive(Clone, Copy)]
+struct BoundingBox {
+    min_x: f64,
+    min_y: f64,
+    min_z: f64,
+    max_x: f64,
+    max_y: f64,
+    max_z: f64,
+}
+
+impl BoundingBox {
+    /// Bounding box of `nodes`, padded by `padding` on every side.
+    fn around(nodes: &[Node], padding: f64) -> Self {
+        let mut min_x = f64::INFINITY;
+        let mut max_x = f64::NEG_INFINITY;
+        let mut min_y = f64::INFINITY;
+        let mut max_y = f64::NEG_INFINITY;
+        let mut min_z = f64::INFINITY;
+        let mut max_z = f64::NEG_INFINITY;
+
+        for node in nodes {
+            min_x = min_x.min(node.x);
+            max_x = max_x.max(node.x);
+            min_y = min_y.min(node.y);
+            max_y = max_y.max(node.y);
+            min_z = min_z.min(node.z);
+            max_z = max_z.max(node.z);
+        }
+
+        BoundingBox {
+            min_x: min_x - padding,
+            min_y: min_y - padding,
+            min_z: min_z - padding,
+            max_x: max_x + padding,
+            max_y: max_y + padding,
+            max_z: max_z + padding,
+        }
+    }
+
+    fn contains(&self, x: f64, y: f64, z: f64) -> bool {
+        x >= self.min_x && x <= self.max_x
+            && y >= self.min_y && y <= self.max_y
+            && z >= self.min_z && z <= self.max_z
+    }
+
+    /// Distance from `(x, y, z)` to the nearest point of the box; `0.0`
+    /// inside it.
+    fn distance_to(&self, x: f64, y: f64, z: f64) -> f64 {
+        let dx = (self.min_x - x).max(x - self.max_x).max(0.0);
+        let dy = (self.min_y - y).max(y - self.max_y).max(0.0);
+        let dz = (self.min_z - z).max(z - self.max_z).max(0.0);
+        (dx * dx + dy * dy + dz * dz).sqrt()
+    }
+
+    fn width(&self) -> f64 {
+        self.max_x - self.min_x
+    }
+
+    fn intersects(&self, other: &BoundingBox) -> bool {
+        self.min_x <= other.max_x && other.min_x <= self.max_x
+            && self.min_y <= other.max_y && other.min_y <= self.max_y
+            && self.min_z <= other.max_z && other.min_z <= self.max_z
+    }
+
+    fn contains_box(&self, other: &BoundingBox) -> bool {
+        self.contains(other.min_x, other.min_y, other.min_z)
+            && self.contains(other.max_x, other.max_y, other.max_z)
+    }
+
+    /// Grows the box by `amount` on every side.
+    fn padded(&self, amount: f64) -> BoundingBox {
+        BoundingBox {
+            min_x: self.min_x - amount,
+            min_y: self.min_y - amount,
+            min_z: self.min_z - amount,
+            max_x: self.max_x + amount,
+            max_y: self.max_y + amount,
+            max_z: self.max_z + amount,
+        }
+    }
+
+    fn union(&self, other: &BoundingBox) -> BoundingBox {
+        let mut merged = *self;
+        merged.expand_to(other.min_x, other.min_y, other.min_z);
+        merged.expand_to(other.max_x, other.max_y, other.max_z);
+        merged
+    }
+
+    fn expand_to(&mut self, x: f64, y: f64, z: f64) {
+        self.min_x = self.min_x.min(x);
+        self.min_y = self.min_y.min(y);
+        self.min_z = self.min_z.min(z);
+        self.max_x = self.max_x.max(x);
+        self.max_y = self.max_y.max(y);
+        self.max_z = self.max_z.max(z);
+    }
+
+    fn subdivide(&self) -> [BoundingBox; 8] {
+        let mid_x = (self.min_x + self.max_x) / 2.0;
+        let mid_y = (self.min_y + self.max_y) / 2.0;
+        let mid_z = (self.min_z + self.max_z) / 2.0;
+
+        [
+            // Bottom layer
+            BoundingBox { min_x: self.min_x, min_y: self.min_y, min_z: self.min_z, max_x: mid_x, max_y: mid_y, max_z: mid_z },
+            BoundingBox { min_x: mid_x, min_y: self.min_y, min_z: self.min_z, max_x: self.max_x, max_y: mid_y, max_z: mid_z },
+            BoundingBox { min_x: self.min_x, min_y: mid_y, min_z: self.min_z, max_x: mid_x, max_y: self.max_y, max_z: mid_z },
+            BoundingBox { min_x: mid_x, min_y: mid_y, min_z: self.min_z, max_x: self.max_x, max_y: self.max_y, max_z: mid_z },
+            // Top layer
+            BoundingBox { min_x: self.min_x, min_y: self.min_y, min_z: mid_z, max_x: mid_x, max_y: mid_y, max_z: self.max_z },
+            BoundingBox { min_x: mid_x, min_y: self.min_y, min_z: mid_z, max_x: self.max_x, max_y: mid_y, max_z: self.max_z },
+            BoundingBox { min_x: self.min_x, min_y: mid_y, min_z: mid_z, max_x: mid_x, max_y: self.max_y, max_z: self.max_z },
+            BoundingBox { min_x: mid_x, min_y: mid_y, min_z: mid_z, max_x: self.max_x, max_y: self.max_y, max_z: self.max_z },
+        ]
+    }
+}
+
+impl QuadTreeNode {
+    fn new(bounds: BoundingBox) -> Self {
+        QuadTreeNode {
+            bounds,
+            center_of_mass: (0.0, 0.0, 0.0),
+            total_mass: 0.0,
+            children: None,
+            node_ids: Vec::new(),
+        }
+    }
+
+    /// Adds `nodes[node_id]` to this cell, which sits `depth` levels below the
+    /// root. A leaf holds a single node; when a second one arrives the cell
+    /// subdivides and both are pushed down. Cells at `MAX_TREE_DEPTH` stay
+    /// leaves so coincident nodes cannot split forever.
+    fn insert(&mut self, node_id: usize, nodes: &[Node], depth: usize) {
+        let node = &nodes[node_id];
+        if !self.bounds.contains(node.x, node.y, node.z) {
+            return;
+        }
+
+        // Update center of mass
+        let new_mass = self.total_mass + node.mass;
+        self.center_of_mass = (
+            (self.center_of_mass.0 * self.total_mass + node.x * node.mass) / new_mass,
+            (self.center_of_mass.1 * self.total_mass + node.y * node.mass) / new_mass,
+            (self.center_of_mass.2 * self.total_mass + node.z * node.mass) / new_mass,
+        );
+        self.total_mass = new_mass;
+
+        if self.children.is_none() {
+            if self.node_ids.is_empty() || depth >= MAX_TREE_DEPTH {
+                // Leaf node, add directly
+                self.node_ids.push(node_id);
+                return;
+            }
+
+            // Need to subdivide
+            let subdivisions = self.bounds.subdivide();
+            self.children = Some(Box::new([
+                QuadTreeNode::new(subdivisions[0]),
+                QuadTreeNode::new(subdivisions[1]),
+                QuadTreeNode::new(subdivisions[2]),
+                QuadTreeNode::new(subdivisions[3]),
+                QuadTreeNode::new(subdivisions[4]),
+                QuadTreeNode::new(subdivisions[5]),
+                QuadTreeNode::new(subdivisions[6]),
+                QuadTreeNode::new(subdivisions[7]),
+            ]));
+
+            // Push the existing node down; it stays in `node_ids`, which lists
+            // every node aggregated into this cell (see `refit`).
+            let existing = self.node_ids[0];
+            self.insert_into_child(existing, nodes, depth);
+        }
+
+        self.insert_into_child(node_id, nodes, depth);
+        self.node_ids.push(node_id);
+    }
+
+    /// Inserts `nodes[node_id]` into the first child whose bounds contain it.
+    fn insert_into_child(&mut self, node_id: usize, nodes: &[Node], depth: usize) {
+        let node = &nodes[node_id];
+        if let Some(ref mut children) = self.children {
+            for child in children.iter_mut() {
+                if child.bounds.contains(node.x, node.y, node.z) {
+                    child.insert(node_id, nodes, depth + 1);
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Recomputes every cell's centre of mass from the current positions of
+    /// the nodes it aggregated when it was built, growing its bounds to cover
+    /// any that have drifted out. The tree structure is left as it is.
+    fn refit(&mut self, nodes: &[Node]) {
+        let mut total_mass = 0.0;
+        let mut weighted = (0.0, 0.0, 0.0);
+        for &id in &self.node_ids {
+            let node = &nodes[id];
+            total_mass += node.mass;
+            weighted.0 += node.x * node.mass;
+            weighted.1 += node.y * node.mass;
+            weighted.2 += node.z * node.mass;
+            self.bounds.expand_to(node.x, node.y, node.z);
+        }
+        self.total_mass = total_mass;
+        self.center_of_mass = (
+            weighted.0 / total_mass,
+            weighted.1 / total_mass,
+            weighted.2 / total_mass,
+        );
+
+        if let Some(ref mut children) = self.children {
+            for child in children.iter_mut() {
+                child.refit(nodes);
+            }
+        }
+    }
+
+    /// Total mass of the nodes inside `region`. Cells entirely inside or
+    /// outside it are answered from their aggregates; only cells straddling
+    /// its border are opened. Assumes the tree is refitted to `nodes`.
+    fn mass_in_region(&self, region: &BoundingBox, nodes: &[Node]) -> f64 {
+        if self.node_ids.is_empty() || !self.bounds.intersects(region) {
+            return 0.0;
+        }
+        if region.contains_box(&self.bounds) {
+            return self.total_mass;
+        }
+
+        let mass_if_inside = |id: usize| {
+            let node = &nodes[id];
+            if region.contains(node.x, node.y, node.z) {
+                node.mass
+            } else {
+                0.0
+            }
+        };
+        match self.children {
+            None => self.node_ids.iter().map(|&id| mass_if_inside(id)).sum(),
+            Some(ref children) => children
+                .iter()
+                .map(|child| child.mass_in_region(region, nodes))
+                .sum(),
+        }
+    }
+
+    /// Calls `visit` with every node aggregated into a cell that reaches
+    /// within `radius` of `(x, y, z)`: a superset of the nodes that close.
+    /// Assumes the tree is refitted to the node positions.
+    fn for_each_near(
+        &self,
+        (x, y, z): (f64, f64, f64),
+        radius: f64,
+        visit: &mut impl FnMut(usize),
+    ) {
+        if self.node_ids.is_empty() || self.bounds.distance_to(x, y, z) > radius {
+            return;
+        }
+        match self.children {
+            None => self.node_ids.iter().for_each(|&id| visit(id)),
+            Some(ref children) => {
+                for child in children.iter() {
+                    child.for_each_near((x, y, z), radius, visit);
+                }
+            }
+        }
+    }
+
+    /// Adds this cell and its subtree, rooted at `depth`, to `stats`, counting
+    /// occupied leaves and the nodes in them alongside.
+    fn collect_stats(&self, depth: usize, stats: &mut TreeStats, leaves: &mut (usize, usize)) {
+        stats.node_count += 1;
+        stats.max_depth = stats.max_depth.max(depth);
+        match self.children {
+            Some(ref children) => {
+                for child in children.iter() {
+                    child.collect_stats(depth + 1, stats, leaves);
+                }
+            }
+            None if !self.node_ids.is_empty() => {
+                leaves.0 += 1;
+                leaves.1 += self.node_ids.len();
+            }
+            None => {}
+        }
+    }
+
+    /// Repulsion on `node` from this cell. Cells lying entirely farther than
+    /// `cutoff` from the node contribute nothing and are not opened.
+    fn calculate_force(&self, node: &Node, theta: f64, cutoff: f64) -> (f64, f64, f64) {
+        if self.total_mass == 0.0 || self.bounds.distance_to(node.x, node.y, node.z) > cutoff {
+            return (0.0, 0.0, 0.0);
+        }
+
+        let dx = self.center_of_mass.0 - node.x;
+        let dy = self.center_of_mass.1 - node.y;
+        let dz = self.center_of_mass.2 - node.z;
+        let dist_sq = dx * dx + dy * dy + dz * dz + 1.0; // Add 1.0 to avoid division by zero
+        let dist = dist_sq.sqrt();
+
+        // Barnes-Hut criterion: if node is far enough, treat as single body
+        if self.children.is_none() || (self.bounds.width() / dist) < theta {
+            // Repulsive force (inverse square law)
+            let force = (node.mass * self.total_mass) / dist_sq;
+            let fx = (dx / dist) * force;
+            let fy = (dy / dist) * force;
+            let fz = (dz / dist) * force;
+            return (fx, fy, fz);
+        }
+
+        // Otherwise, recurse into children
+        let mut total_force = (0.0, 0.0, 0.0);
+        if let Some(ref children) = self.children {
+            for child in children.iter() {
+                let child_force = child.calculate_force(node, theta, cutoff);
+                total_force.0 += child_force.0;
+                total_force.1 += child_force.1;
+                total_force.2 += child_force.2;
+            }
+        }
+        total_force
+    }
+}
+
+// Physics simulation engine
+pub struct Engine {
+    nodes: Vec<Node>,
+    edges: Vec<Edge>,
+    edge_weights: Vec<f64>,
+    weight_normalization: WeightNormalization,
+    node_map: HashMap<String, usize>,
+    repulsion_strength: f64,
+    attraction_strength: f64,
+    damping: f64,
+    theta: f64, // Barnes-Hut threshold
+    repulsion_cutoff: f64,
+    tree: Option<QuadTreeNode>,
+    refit_interval: u32,
+    ticks_since_rebuild: u32,
+    schedule: Vec<SchedulePhase>,
+    schedule_ticks: u64,
+    radial: Option<RadialLayout>,
+    ring_radii: Vec<f64>,
+    directional: Option<(Axis, f64)>,
+    anchors: HashMap<String, ([f64; 3], f64)>,
+    bounds: Option<BoundingBox>,
+    bounds_margin: f64,
+    bounds_shrink_delay: u32,
+    ticks_oversized: u32,
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Engine {
+    pub fn new() -> Self {
+        Engine {
+            nodes: Vec::new(),
+            edges: Vec::new(),
+            edge_weights: Vec::new(),
+            weight_normalization: WeightNormalization::None,
+            node_map: HashMap::new(),
+            repulsion_strength: 1000.0,
+            attraction_strength: 0.01,
+            damping: 0.8,
+            theta: 0.5,
+            repulsion_cutoff: f64::INFINITY,
+            tree: None,
+            refit_interval: 1,
+            ticks_since_rebuild: 0,
+            schedule: Vec::new(),
+            schedule_ticks: 0,
+            radial: None,
+            ring_radii: Vec::new(),
+            directional: None,
+            anchors: HashMap::new(),
+            bounds: None,
+            bounds_margin: 100.0,
+            bounds_shrink_delay: 0,
+            ticks_oversized: 0,
+        }
+    }
+
+    pub fn nodes(&self) -> &[Node] {
+        &self.nodes
+    }
+
+    /// The node with the given id, if any.
+    pub fn node(&self, id: &str) -> Option<&Node> {
+        self.node_map.get(id).map(|&idx| &self.nodes[idx])
+    }
+
+    /// Mutable access for in-place updates that keep ids and edges intact.
+    pub fn nodes_mut(&mut self) -> &mut [Node] {
+        &mut self.nodes
+    }
+
+    pub fn edges(&self) -> &[Edge] {
+        &self.edges
+    }
+
+    pub fn set_nodes(&mut self, nodes: Vec<Node>) {
+        self.node_map.clear();
+        for (idx, node) in nodes.iter().enumerate() {
+            self.node_map.insert(node.id.clone(), idx);
+        }
+        self.nodes = nodes;
+        self.tree = None;
+        self.bounds = None;
+        self.update_ring_radii();
+    }
+
+    pub fn set_edges(&mut self, edges: Vec<Edge>) {
+        self.edge_weights = self.weight_normalization.apply(&edges);
+        self.edges = edges;
+        self.update_ring_radii();
+    }
+
+    /// Pushes every edge's target towards larger `axis` coordinates than its
+    /// source, applying `strength * weight * (1 - cos θ)` along the axis to
+    /// both ends in opposite directions, where θ is the edge's angle to the
+    /// axis. Aligned edges feel nothing, so together with the springs this
+    /// lays a DAG out in layers. A `strength` of `0` turns it off.
+    pub fn set_directional_force(&mut self, axis: Axis, strength: f64) {
+        self.directional = (strength != 0.0).then_some((axis, strength));
+    }
+
+    /// Ties node `id` to `position` with a spring of force
+    /// `strength * (position - pos)`. Unlike a pin, the node still moves under
+    /// the other forces, so it settles near the anchor rather than on it.
+    /// Anchors are kept by id and survive [`set_nodes`](Self::set_nodes);
+    /// ids without a node are ignored.
+    pub fn set_anchor(&mut self, id: &str, position: [f64; 3], strength: f64) {
+        self.anchors.insert(id.to_string(), (position, strength));
+    }
+
+    /// Removes the anchor of node `id`, returning whether it had one.
+    pub fn clear_anchor(&mut self, id: &str) -> bool {
+        self.anchors.remove(id).is_some()
+    }
+
+    /// Enables the concentric-ring layout, or disables it with `None`.
+    pub fn set_radial_layout(&mut self, layout: Option<RadialLayout>) {
+        self.radial = layout;
+        self.update_ring_radii();
+    }
+
+    /// Recomputes each node's target ring radius for the radial layout.
+    fn update_ring_radii(&mut self) {
+        let Some(radial) = self.radial else {
+            self.ring_radii.clear();
+            return;
+        };
+        let values: Vec<f64> = match radial.rank_by {
+            RankAttribute::Mass => self.nodes.iter().map(|n| n.mass).collect(),
+            RankAttribute::Degree => {
+                let mut degree = vec![0.0; self.nodes.len()];
+                for edge in &self.edges {
+                    for id in [&edge.source, &edge.target] {
+                        if let Some(&idx) = self.node_map.get(id) {
+                            degree[idx] += 1.0;
+                        }
+                    }
+                }
+                degree
+            }
+        };
+        let mut levels = values.clone();
+        levels.sort_by(|a, b| b.total_cmp(a));
+        levels.dedup();
+        self.ring_radii = values
+            .iter()
+            .map(|v| {
+                let rank = levels.partition_point(|level| level > v);
+                rank as f64 * radial.ring_spacing
+            })
+            .collect();
+    }
+
+    /// Selects how edge weights are rescaled for the spring forces, and
+    /// rescales the current edges accordingly.
+    pub fn set_weight_normalization(&mut self, mode: WeightNormalization) {
+        self.weight_normalization = mode;
+        self.edge_weights = mode.apply(&self.edges);
+    }
+
+    /// Weights the spring forces actually use, in edge order.
+    pub fn effective_weights(&self) -> &[f64] {
+        &self.edge_weights
+    }
+
+    pub fn set_params(&mut self, repulsion: f64, attraction: f64, damping: f64, theta: f64) {
+        self.repulsion_strength = repulsion;
+        self.attraction_strength = attraction;
+        self.damping = damping;
+        self.theta = theta;
+    }
+
+    /// Ignores repulsion from octree cells lying entirely more than `radius`
+    /// from a node, so distant parts of a large graph cost no traversal.
+    /// `f64::INFINITY` (the default) keeps every interaction.
+    pub fn set_repulsion_cutoff(&mut self, radius: f64) {
+        self.repulsion_cutoff = radius;
+    }
+
+    /// Rebuilds the Barnes-Hut octree only every `interval` ticks and refits
+    /// the existing one in between, which is cheaper while nodes move little.
+    /// The refitted tree keeps its old structure, so repulsion gets less
+    /// accurate until the next rebuild. `0` and `1` rebuild on every tick.
+    pub fn set_refit_interval(&mut self, interval: u32) {
+        self.refit_interval = interval;
+    }
+
+    /// Controls how the octree's box follows the nodes. It always keeps
+    /// `margin` of room around them, growing as soon as a node comes closer
+    /// to an edge than that. With `shrink_delay` above zero it only shrinks
+    /// back once it has been more than `margin` too large on some side for
+    /// `shrink_delay` consecutive rebuilds, so a node that briefly escapes
+    /// does not resize the tree twice. `0` (the default) refits the box to
+    /// the nodes on every rebuild.
+    pub fn set_bounds_hysteresis(&mut self, margin: f64, shrink_delay: u32) {
+        self.bounds_margin = margin;
+        self.bounds_shrink_delay = shrink_delay;
+        self.ticks_oversized = 0;
+    }
+
+    /// Current octree box as `([min_x, min_y, min_z], [max_x, max_y,
+    /// max_z])`, or `None` before the first tick.
+    pub fn octree_bounds(&self) -> Option<([f64; 3], [f64; 3])> {
+        self.bounds
+            .map(|b| ([b.min_x, b.min_y, b.min_z], [b.max_x, b.max_y, b.max_z]))
+    }
+
+    /// Box for the next tree rebuild, applying the hysteresis configured by
+    /// [`set_bounds_hysteresis`](Self::set_bounds_hysteresis).
+    fn next_bounds(&mut self) -> BoundingBox {
+        let target = BoundingBox::around(&self.nodes, self.bounds_margin);
+        let bounds = match self.bounds {
+            Some(current) if self.bounds_shrink_delay > 0 => {
+                if !current.contains_box(&target) {
+                    self.ticks_oversized = 0;
+                    current.union(&target)
+                } else if !target.padded(self.bounds_margin).contains_box(&current) {
+                    self.ticks_oversized += 1;
+                    if self.ticks_oversized >= self.bounds_shrink_delay {
+                        self.ticks_oversized = 0;
+                        target
+                    } else {
+                        current
+                    }
+                } else {
+                    self.ticks_oversized = 0;
+                    current
+                }
+            }
+            _ => target,
+        };
+        self.bounds = Some(bounds);
+        bounds
+    }
+
+    /// Replaces the annealing schedule and restarts it. The temperature caps
+    /// how far any node may move in one tick; each phase holds its
+    /// temperature for its number of ticks, then the next one takes over, and
+    /// the last phase's temperature is kept once the schedule has run out. An
+    /// empty schedule removes the cap. Phases with `ticks == 0` are skipped,
+    /// so a schedule made only of them starts at its last phase.
+    ///
+    /// Fails, leaving the current schedule in place, if any temperature is
+    /// negative or not finite.
+    pub fn set_schedule(&mut self, phases: Vec<SchedulePhase>) -> Result<(), String> {
+        if let Some((idx, phase)) = phases
+            .iter()
+            .enumerate()
+            .find(|(_, phase)| !(phase.temperature.is_finite() && phase.temperature >= 0.0))
+        {
+            return Err(format!(
+                "schedule phase {} has invalid temperature {}",
+                idx, phase.temperature
+            ));
+        }
+        self.schedule = phases;
+        self.schedule_ticks = 0;
+        Ok(())
+    }
+
+    /// Index of the schedule phase the next tick runs in; the last phase once
+    /// the schedule is complete, `None` without a schedule.
+    pub fn current_phase(&self) -> Option<usize> {
+        let mut remaining = self.schedule_ticks;
+        for (idx, phase) in self.schedule.iter().enumerate() {
+            if remaining < phase.ticks as u64 {
+                return Some(idx);
+            }
+            remaining -= phase.ticks as u64;
+        }
+        self.schedule.len().checked_sub(1)
+    }
+
+    /// Largest distance a node may move in the next tick, or infinity when no
+    /// schedule is set.
+    pub fn temperature(&self) -> f64 {
+        self.current_phase()
+            .map_or(f64::INFINITY, |idx| self.schedule[idx].temperature)
+    }
+
+    /// Total mass of the nodes inside the axis-aligned box `min..=max`, found
+    /// by walking the octree rather than every node. The cached tree is
+    /// refitted to the current positions first.
+    pub fn mass_in_region(&mut self, min: [f64; 3], max: [f64; 3]) -> f64 {
+        if self.nodes.is_empty() {
+            return 0.0;
+        }
+        let region = BoundingBox {
+            min_x: min[0],
+            min_y: min[1],
+            min_z: min[2],
+            max_x: max[0],
+            max_y: max[1],
+            max_z: max[2],
+        };
+        self.with_tree(|tree, nodes| tree.mass_in_region(&region, nodes))
+    }
+
+    /// Number of node pairs closer than the sum of their radii, for checking
+    /// a settled layout. Nodes without a [`radius`](Node::radius) use
+    /// `default_radius`. Candidates come from an octree built for the query,
+    /// so this avoids comparing every pair and leaves the cached tree alone.
+    /// Diagnostic only: it applies no force.
+    pub fn overlapping_pairs(&self, default_radius: f64) -> usize {
+        if self.nodes.is_empty() {
+            return 0;
+        }
+        let radius_of = |node: &Node| node.radius.unwrap_or(default_radius);
+        let max_radius = self.nodes.iter().map(radius_of).fold(0.0, f64::max);
+        let tree = build_tree(&self.nodes, BoundingBox::around(&self.nodes, 0.0));
+
+        let mut count = 0;
+        for (idx, node) in self.nodes.iter().enumerate() {
+            let r = radius_of(node);
+            tree.for_each_near((node.x, node.y, node.z), r + max_radius, &mut |other| {
+                let o = &self.nodes[other];
+                let (dx, dy, dz) = (o.x - node.x, o.y - node.y, o.z - node.z);
+                let reach = r + radius_of(o);
+                if other > idx && dx * dx + dy * dy + dz * dz < reach * reach {
+                    count += 1;
+                }
+            });
+        }
+        count
+    }
+
+    /// Runs `query` on the cached tree refitted to the current positions, or
+    /// on a temporary tree when none has been built yet.
+    fn with_tree<R>(&mut self, query: impl FnOnce(&QuadTreeNode, &[Node]) -> R) -> R {
+        match self.tree.as_mut() {
+            Some(tree) => {
+                tree.refit(&self.nodes);
+                query(tree, &self.nodes)
+            }
+            None => {
+                let bounds = BoundingBox::around(&self.nodes, self.bounds_margin);
+                query(&build_tree(&self.nodes, bounds), &self.nodes)
+            }
+        }
+    }
+
+    /// Size and depth of the octree the last [`tick`](Engine::tick) used, or
+    /// `None` before the first tick (and after the nodes are replaced).
+    pub fn tree_stats(&self) -> Option<TreeStats> {
+        let tree = self.tree.as_ref()?;
+        let mut stats = TreeStats::default();
+        let mut leaves = (0, 0);
+        tree.collect_stats(0, &mut stats, &mut leaves);
+        if leaves.0 > 0 {
+            stats.average_leaf_occupancy = leaves.1 as f64 / leaves.0 as f64;
+        }
+        Some(stats)
+    }
+
+    /// Mass-weighted centroid of all nodes at their current positions, or the
+    /// origin when there are none (or their total mass is zero).
+    pub fn center_of_mass(&self) -> (f64, f64, f64) {
+        let mut total_mass = 0.0;
+        let mut weighted = (0.0, 0.0, 0.0);
+        for node in &self.nodes {
+            total_mass += node.mass;
+            weighted.0 += node.x * node.mass;
+            weighted.1 += node.y * node.mass;
+            weighted.2 += node.z * node.mass;
+        }
+        if total_mass == 0.0 {
+            return (0.0, 0.0, 0.0);
+        }
+        (
+            weighted.0 / total_mass,
+            weighted.1 / total_mass,
+            weighted.2 / total_mass,
+        )
+    }
+
+    /// Total kinetic energy `sum(0.5 * m * |v|^2)` of all nodes.
+    pub fn kinetic_energy(&self) -> f64 {
+        self.nodes
+            .iter()
+            .map(|n| 0.5 * n.mass * (n.vx * n.vx + n.vy * n.vy + n.vz * n.vz))
+            .sum()
+    }
+
+    /// Whether the layout has settled, i.e. its kinetic energy is at or below
+    /// `threshold`.
+    pub fn has_converged(&self, threshold: f64) -> bool {
+        self.kinetic_energy() <= threshold
+    }
+
+    /// Advances the simulation by `delta_time`: Barnes-Hut repulsion between
+    /// all nodes, spring attraction along edges, then damped integration.
+    pub fn tick(&mut self, delta_time: f64) {
+        if self.nodes.is_empty() {
+            return;
+        }
+
+        let rebuild = self.tree.is_none() || self.ticks_since_rebuild >= self.refit_interval;
+        let tree = match self.tree.as_mut() {
+            Some(tree) if !rebuild => {
+                tree.refit(&self.nodes);
+                self.ticks_since_rebuild += 1;
+                tree
+            }
+            _ => {
+                self.ticks_since_rebuild = 1;
+                let bounds = self.next_bounds();
+                self.tree.insert(build_tree(&self.nodes, bounds))
+            }
+        };
+
+        // Calculate repulsive forces using Barnes-Hut
+        let mut forces: Vec<(f64, f64, f64)> = Vec::with_capacity(self.nodes.len());
+        for node in &self.nodes {
+            let force = tree.calculate_force(node, self.theta, self.repulsion_cutoff);
+            forces.push((
+                force.0 * self.repulsion_strength,
+                force.1 * self.repulsion_strength,
+                force.2 * self.repulsion_strength,
+            ));
+        }
+
+        // Calculate attractive forces from edges (Hooke's law)
+        for (edge, &weight) in self.edges.iter().zip(&self.edge_weights) {
+            if let (Some(&source_idx), Some(&target_idx)) = 
+                (self.node_map.get(&edge.source), self.node_map.get(&edge.target)) {
+                
+                let source = &self.nodes[source_idx];
+                let target = &self.nodes[target_idx];
+
+                let dx = target.x - source.x;
+                let dy = target.y - source.y;
+                let dz = target.z - source.z;
+                let dist = (dx * dx + dy * dy + dz * dz).sqrt().max(0.1);
+
+                let force = self.attraction_strength * dist * weight;
+                let fx = (dx / dist) * force;
+                let fy = (dy / dist) * force;
+                let fz = (dz / dist) * force;
+
+                forces[source_idx].0 += fx;
+                forces[source_idx].1 += fy;
+                forces[source_idx].2 += fz;
+                forces[target_idx].0 -= fx;
+                forces[target_idx].1 -= fy;
+                forces[target_idx].2 -= fz;
+
+                if let Some((axis, strength)) = self.directional {
+                    let along = axis.of((dx, dy, dz));
+                    let push = strength * weight * (1.0 - along / dist);
+                    *axis.component(&mut forces[source_idx]) -= push;
+                    *axis.component(&mut forces[target_idx]) += push;
+                }
+            }
+        }
+
+        // Pull nodes onto their rings
+        if let Some(radial) = self.radial {
+            let (cx, cy, cz) = self.center_of_mass();
+            for (idx, node) in self.nodes.iter().enumerate() {
+                let (dx, dy, dz) = (node.x - cx, node.y - cy, node.z - cz);
+                let radius = (dx * dx + dy * dy + dz * dz).sqrt();
+                if radius > 1e-9 {
+                    let pull = radial.strength * (self.ring_radii[idx] - radius) / radius;
+                    forces[idx].0 += dx * pull;
+                    forces[idx].1 += dy * pull;
+                    forces[idx].2 += dz * pull;
+                }
+            }
+        }
+
+        // Pull anchored nodes towards their anchors
+        for (id, &(anchor, strength)) in &self.anchors {
+            if let Some(&idx) = self.node_map.get(id) {
+                let node = &self.nodes[idx];
+                forces[idx].0 += strength * (anchor[0] - node.x);
+                forces[idx].1 += strength * (anchor[1] - node.y);
+                forces[idx].2 += strength * (anchor[2] - node.z);
+            }
+        }
+
+        // Apply forces and update positions
+        let max_step = self.temperature();
+        for (idx, node) in self.nodes.iter_mut().enumerate() {
+            // Apply force to velocity
+            node.vx += forces[idx].0 * delta_time;
+            node.vy += forces[idx].1 * delta_time;
+            node.vz += forces[idx].2 * delta_time;
+
+            // Apply damping
+            node.vx *= self.damping;
+            node.vy *= self.damping;
+            node.vz *= self.damping;
+
+            // Cap the step at the current temperature
+            let step = (node.vx * node.vx + node.vy * node.vy + node.vz * node.vz).sqrt()
+                * delta_time.abs();
+            if step > max_step {
+                let scale = max_step / step;
+                node.vx *= scale;
+                node.vy *= scale;
+                node.vz *= scale;
+            }
+
+            // Update position
+            node.x += node.vx * delta_time;
+            node.y += node.vy * delta_time;
+            node.z += node.vz * delta_time;
+        }
+        if !self.schedule.is_empty() {
+            self.schedule_ticks += 1;
+        }
+    }
+}
+
+/// Builds the Barnes-Hut octree over `nodes` inside `bounds`.
+fn build_tree(nodes: &[Node], bounds: BoundingBox) -> QuadTreeNode {
+    let mut tree = QuadTreeNode::new(bounds);
+    for idx in 0..nodes.len() {
+        tree.insert(idx, nodes, 0);
+    }
+    tree
+}
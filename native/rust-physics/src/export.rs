@@ -0,0 +1,108 @@
+//! Layout snapshots in formats other graph tools read: a plain JSON document
+//! and GraphML (Gephi, Cytoscape, yEd).
+
+use std::fmt::Write;
+
+use serde::Serialize;
+
+use crate::engine::{Edge, Engine, Node};
+
+/// Output format for [`Engine::export_graph`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GraphFormat {
+    /// `{"nodes": [...], "edges": [...]}` with the same fields as [`Node`]
+    /// and [`Edge`].
+    Json,
+    /// GraphML with `x`/`y`/`z`/`mass` node keys and a `weight` edge key.
+    GraphMl,
+}
+
+#[derive(Serialize)]
+struct GraphSnapshot<'a> {
+    nodes: &'a [Node],
+    edges: &'a [Edge],
+}
+
+impl Engine {
+    /// Serializes the current nodes (with positions) and edges.
+    pub fn export_graph(&self, format: GraphFormat) -> String {
+        match format {
+            GraphFormat::Json => {
+                let snapshot = GraphSnapshot {
+                    nodes: self.nodes(),
+                    edges: self.edges(),
+                };
+                serde_json::to_string(&snapshot).expect("graph snapshot is always serializable")
+            }
+            GraphFormat::GraphMl => write_graphml(self.nodes(), self.edges()),
+        }
+    }
+}
+
+fn write_graphml(nodes: &[Node], edges: &[Edge]) -> String {
+    let mut out = String::new();
+    // Writing into a String cannot fail.
+    let _ = write_graphml_into(&mut out, nodes, edges);
+    out
+}
+
+fn write_graphml_into(out: &mut String, nodes: &[Node], edges: &[Edge]) -> std::fmt::Result {
+    writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(
+        out,
+        r#"<graphml xmlns="http://graphml.graphdrawing.org/xmlns">"#
+    )?;
+    for key in ["x", "y", "z", "mass"] {
+        writeln!(
+            out,
+            r#"  <key id="{0}" for="node" attr.name="{0}" attr.type="double"/>"#,
+            key
+        )?;
+    }
+    writeln!(
+        out,
+        r#"  <key id="weight" for="edge" attr.name="weight" attr.type="double"/>"#
+    )?;
+    writeln!(out, r#"  <graph id="G" edgedefault="undirected">"#)?;
+    for node in nodes {
+        writeln!(out, r#"    <node id="{}">"#, escape(&node.id))?;
+        for (key, value) in [
+            ("x", node.x),
+            ("y", node.y),
+            ("z", node.z),
+            ("mass", node.mass),
+        ] {
+            writeln!(out, r#"      <data key="{}">{}</data>"#, key, value)?;
+        }
+        writeln!(out, "    </node>")?;
+    }
+    for (idx, edge) in edges.iter().enumerate() {
+        writeln!(
+            out,
+            r#"    <edge id="e{}" source="{}" target="{}">"#,
+            idx,
+            escape(&edge.source),
+            escape(&edge.target)
+        )?;
+        writeln!(out, r#"      <data key="weight">{}</data>"#, edge.weight)?;
+        writeln!(out, "    </edge>")?;
+    }
+    writeln!(out, "  </graph>")?;
+    writeln!(out, "</graphml>")
+}
+
+/// Escapes the characters that are not allowed verbatim in XML attributes.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,307 @@
+use wasm_bindgen::prelude::*;
+
+use crate::engine::{
+    Axis, Edge, Engine, Node, RadialLayout, RankAttribute, SchedulePhase, WeightNormalization,
+};
+use crate::export::GraphFormat;
+
+// Physics simulation engine
+#[wasm_bindgen]
+#[derive(Default)]
+pub struct PhysicsEngine {
+    engine: Engine,
+}
+
+#[wasm_bindgen]
+impl PhysicsEngine {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        PhysicsEngine {
+            engine: Engine::new(),
+        }
+    }
+
+    #[wasm_bindgen(js_name = setNodes)]
+    pub fn set_nodes(&mut self, nodes_js: JsValue) -> Result<(), JsValue> {
+        let nodes: Vec<Node> = serde_wasm_bindgen::from_value(nodes_js)?;
+        self.engine.set_nodes(nodes);
+        Ok(())
+    }
+
+    #[wasm_bindgen(js_name = setEdges)]
+    pub fn set_edges(&mut self, edges_js: JsValue) -> Result<(), JsValue> {
+        let edges: Vec<Edge> = serde_wasm_bindgen::from_value(edges_js)?;
+        self.engine.set_edges(edges);
+        Ok(())
+    }
+
+    /// Rescales edge weights for the spring forces: `"none"` (the default),
+    /// `"maxScale"` (divide by the largest weight) or `"log"` (`ln(1 + w)`).
+    /// `getEdges` still reports the weights as they were set.
+    #[wasm_bindgen(js_name = setWeightNormalization)]
+    pub fn set_weight_normalization(&mut self, mode: &str) -> Result<(), JsValue> {
+        let mode = match mode {
+            "none" => WeightNormalization::None,
+            "maxScale" => WeightNormalization::MaxScale,
+            "log" => WeightNormalization::Log,
+            _ => {
+                return Err(JsValue::from_str(&format!(
+                    "unknown weight normalization {:?}",
+                    mode
+                )))
+            }
+        };
+        self.engine.set_weight_normalization(mode);
+        Ok(())
+    }
+
+    /// Lays nodes out on concentric rings ranked by `attr` (`"degree"` or
+    /// `"mass"`), largest in the centre and `ringSpacing` (default 100) apart.
+    /// Passing `enabled = false` turns the layout off.
+    #[wasm_bindgen(js_name = setRadialLayout)]
+    pub fn set_radial_layout(
+        &mut self,
+        enabled: bool,
+        attr: &str,
+        ring_spacing: Option<f64>,
+    ) -> Result<(), JsValue> {
+        if !enabled {
+            self.engine.set_radial_layout(None);
+            return Ok(());
+        }
+        let rank_by = match attr {
+            "degree" => RankAttribute::Degree,
+            "mass" => RankAttribute::Mass,
+            _ => {
+                return Err(JsValue::from_str(&format!(
+                    "unknown rank attribute {:?}",
+                    attr
+                )))
+            }
+        };
+        let mut layout = RadialLayout {
+            rank_by,
+            ..RadialLayout::default()
+        };
+        if let Some(spacing) = ring_spacing {
+            layout.ring_spacing = spacing;
+        }
+        self.engine.set_radial_layout(Some(layout));
+        Ok(())
+    }
+
+    /// Pushes edge targets towards higher `axis` (`"x"`, `"y"` or `"z"`)
+    /// coordinates than their sources, for layered DAG layouts. A `strength`
+    /// of `0` turns it off.
+    #[wasm_bindgen(js_name = setDirectionalForce)]
+    pub fn set_directional_force(&mut self, axis: &str, strength: f64) -> Result<(), JsValue> {
+        let axis = match axis {
+            "x" => Axis::X,
+            "y" => Axis::Y,
+            "z" => Axis::Z,
+            _ => return Err(JsValue::from_str(&format!("unknown axis {:?}", axis))),
+        };
+        self.engine.set_directional_force(axis, strength);
+        Ok(())
+    }
+
+    /// Pulls node `id` towards `(x, y, z)` with a spring of the given
+    /// `strength`, loosely constraining it without pinning it.
+    #[wasm_bindgen(js_name = setAnchor)]
+    pub fn set_anchor(&mut self, id: &str, x: f64, y: f64, z: f64, strength: f64) {
+        self.engine.set_anchor(id, [x, y, z], strength);
+    }
+
+    /// Removes the anchor of node `id`; returns whether it had one.
+    #[wasm_bindgen(js_name = clearAnchor)]
+    pub fn clear_anchor(&mut self, id: &str) -> bool {
+        self.engine.clear_anchor(id)
+    }
+
+    #[wasm_bindgen(js_name = getEdges)]
+    pub fn get_edges(&self) -> Result<JsValue, JsValue> {
+        Ok(serde_wasm_bindgen::to_value(self.engine.edges())?)
+    }
+
+    /// Snapshots the graph as `"json"` (nodes with positions plus edges) or
+    /// `"graphml"` for tools such as Gephi and Cytoscape.
+    #[wasm_bindgen(js_name = exportGraph)]
+    pub fn export_graph(&self, format: &str) -> Result<String, JsValue> {
+        let format = match format {
+            "json" => GraphFormat::Json,
+            "graphml" => GraphFormat::GraphMl,
+            _ => {
+                return Err(JsValue::from_str(&format!(
+                    "unknown graph format {:?}",
+                    format
+                )))
+            }
+        };
+        Ok(self.engine.export_graph(format))
+    }
+
+    #[wasm_bindgen(js_name = setParams)]
+    pub fn set_params(&mut self, repulsion: f64, attraction: f64, damping: f64, theta: f64) {
+        self.engine.set_params(repulsion, attraction, damping, theta);
+    }
+
+    /// Skips repulsion from tree cells entirely beyond `radius` of a node.
+    /// `Infinity` (the default) disables the cutoff.
+    #[wasm_bindgen(js_name = setRepulsionCutoff)]
+    pub fn set_repulsion_cutoff(&mut self, radius: f64) {
+        self.engine.set_repulsion_cutoff(radius);
+    }
+
+    /// Rebuilds the Barnes-Hut tree only every `interval` ticks, refitting it
+    /// in between. `1` (the default) rebuilds on every tick.
+    #[wasm_bindgen(js_name = setRefitInterval)]
+    pub fn set_refit_interval(&mut self, interval: u32) {
+        self.engine.set_refit_interval(interval);
+    }
+
+    /// Keeps `margin` of room around the nodes in the Barnes-Hut box and only
+    /// shrinks it after it has been oversized for `shrinkDelay` rebuilds, so
+    /// a briefly escaping node does not thrash the tree. A delay of `0` (the
+    /// default) refits the box every rebuild.
+    #[wasm_bindgen(js_name = setBoundsHysteresis)]
+    pub fn set_bounds_hysteresis(&mut self, margin: f64, shrink_delay: u32) {
+        self.engine.set_bounds_hysteresis(margin, shrink_delay);
+    }
+
+    /// Sets an annealing schedule from a list of `{ temperature, ticks }`
+    /// phases, stepped through automatically as the engine ticks. The
+    /// temperature caps how far a node moves per tick; the last phase's value
+    /// holds once the schedule is done. Throws if a temperature is negative or
+    /// not finite.
+    #[wasm_bindgen(js_name = setSchedule)]
+    pub fn set_schedule(&mut self, phases_js: JsValue) -> Result<(), JsValue> {
+        let phases: Vec<SchedulePhase> = serde_wasm_bindgen::from_value(phases_js)?;
+        self.engine
+            .set_schedule(phases)
+            .map_err(|err| JsValue::from_str(&err))
+    }
+
+    /// Index of the schedule phase the next tick runs in, or `undefined`
+    /// without a schedule.
+    #[wasm_bindgen(js_name = currentPhase)]
+    pub fn current_phase(&self) -> Option<u32> {
+        self.engine.current_phase().map(|idx| idx as u32)
+    }
+
+    pub fn temperature(&self) -> f64 {
+        self.engine.temperature()
+    }
+
+    #[wasm_bindgen(js_name = tick)]
+    pub fn tick(&mut self, delta_time: f64) -> Result<JsValue, JsValue> {
+        self.engine.tick(delta_time);
+        Ok(serde_wasm_bindgen::to_value(self.engine.nodes())?)
+    }
+
+    /// Packed `[x0, y0, z0, x1, ...]` positions in node order, without going
+    /// through serde.
+    #[wasm_bindgen(js_name = getPositions)]
+    pub fn get_positions(&self) -> Vec<f32> {
+        self.engine
+            .nodes()
+            .iter()
+            .flat_map(|n| [n.x as f32, n.y as f32, n.z as f32])
+            .collect()
+    }
+
+    /// Overwrites node positions from a packed `[x, y, z]` array in node
+    /// order. Ids, masses, velocities and edges are left as they are.
+    #[wasm_bindgen(js_name = setPositions)]
+    pub fn set_positions(&mut self, positions: &[f32]) -> Result<(), JsValue> {
+        let nodes = self.engine.nodes_mut();
+        if positions.len() != nodes.len() * 3 {
+            return Err(JsValue::from_str(&format!(
+                "positions length {} does not match expected {}",
+                positions.len(),
+                nodes.len() * 3
+            )));
+        }
+        for (node, p) in nodes.iter_mut().zip(positions.chunks_exact(3)) {
+            node.x = p[0] as f64;
+            node.y = p[1] as f64;
+            node.z = p[2] as f64;
+        }
+        Ok(())
+    }
+
+    /// Mass-weighted `[x, y, z]` centroid of the current positions, e.g. for
+    /// framing the camera.
+    #[wasm_bindgen(js_name = centerOfMass)]
+    pub fn center_of_mass(&self) -> Result<JsValue, JsValue> {
+        let (x, y, z) = self.engine.center_of_mass();
+        Ok(serde_wasm_bindgen::to_value(&[x, y, z])?)
+    }
+
+    /// Total mass of the nodes inside the box from `min` to `max`, each an
+    /// `[x, y, z]` array, answered from the Barnes-Hut tree.
+    #[wasm_bindgen(js_name = massInRegion)]
+    pub fn mass_in_region(&mut self, min: &[f64], max: &[f64]) -> Result<f64, JsValue> {
+        let corner = |name: &str, values: &[f64]| -> Result<[f64; 3], JsValue> {
+            values.try_into().map_err(|_| {
+                JsValue::from_str(&format!(
+                    "{} must have 3 components, got {}",
+                    name,
+                    values.len()
+                ))
+            })
+        };
+        let (min, max) = (corner("min", min)?, corner("max", max)?);
+        Ok(self.engine.mass_in_region(min, max))
+    }
+
+    /// `{ nodeCount, maxDepth, averageLeafOccupancy }` for the octree the
+    /// last tick built, or `null` before the first tick.
+    #[wasm_bindgen(js_name = treeStats)]
+    pub fn tree_stats(&self) -> Result<JsValue, JsValue> {
+        match self.engine.tree_stats() {
+            Some(stats) => Ok(serde_wasm_bindgen::to_value(&stats)?),
+            None => Ok(JsValue::NULL),
+        }
+    }
+
+    /// Number of node pairs closer than the sum of their radii, counted via
+    /// an octree; nodes without a `radius` use `default_radius`. A layout
+    /// quality check; it does not move anything.
+    #[wasm_bindgen(js_name = overlappingPairs)]
+    pub fn overlapping_pairs(&self, default_radius: f64) -> u32 {
+        self.engine.overlapping_pairs(default_radius) as u32
+    }
+
+    /// Total kinetic energy of the layout; drops towards zero as it settles.
+    #[wasm_bindgen(js_name = kineticEnergy)]
+    pub fn kinetic_energy(&self) -> f64 {
+        self.engine.kinetic_energy()
+    }
+
+    /// Whether the kinetic energy is at or below `threshold`, so the render
+    /// loop can stop ticking.
+    pub fn converged(&self, threshold: f64) -> bool {
+        self.engine.has_converged(threshold)
+    }
+
+    #[wasm_bindgen(js_name = getNodes)]
+    pub fn get_nodes(&self) -> Result<JsValue, JsValue> {
+        Ok(serde_wasm_bindgen::to_value(self.engine.nodes())?)
+    }
+
+    /// Current data of the single node `id`, e.g. for a hover tooltip,
+    /// without serializing the whole graph. Errors on an unknown id.
+    #[wasm_bindgen(js_name = getNode)]
+    pub fn get_node(&self, id: &str) -> Result<JsValue, JsValue> {
+        let node = self
+            .engine
+            .node(id)
+            .ok_or_else(|| JsValue::from_str(&format!("unknown node id {:?}", id)))?;
+        Ok(serde_wasm_bindgen::to_value(node)?)
+    }
+}
+
+#[wasm_bindgen(start)]
+pub fn main() {
+    // Future: add console_error_panic_hook for better error messages
+}
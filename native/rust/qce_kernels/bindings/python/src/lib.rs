@@ -1,62 +1,386 @@
+use glyph_physics::{Edge, Engine, Node};
+use numpy::ndarray::Dimension;
+use numpy::{
+    AllowTypeChange, Element, IntoPyArray, PyArray1, PyArray2, PyArrayLike1, PyArrayLike2,
+    PyArrayMethods, PyReadonlyArray, PyReadonlyArray1, PyReadonlyArray2, PyUntypedArrayMethods,
+};
+use pyo3::create_exception;
 use pyo3::exceptions::PyValueError;
 use pyo3::prelude::*;
 use qce_kernels::kernels::{coherence, ssr, taa};
+use qce_kernels::KernelError;
+
+create_exception!(
+    qce_kernels_py,
+    LengthMismatchError,
+    PyValueError,
+    "A buffer's length does not match the image dimensions."
+);
+create_exception!(
+    qce_kernels_py,
+    DimensionOverflowError,
+    PyValueError,
+    "The image dimensions overflow when computing a buffer length."
+);
+
+/// Maps a core error onto the matching Python exception; everything without a
+/// dedicated class stays a plain `ValueError`.
+fn kernel_error(err: KernelError) -> PyErr {
+    match err {
+        KernelError::LengthMismatch { .. } => LengthMismatchError::new_err(err.to_string()),
+        KernelError::DimensionOverflow => DimensionOverflowError::new_err(err.to_string()),
+        _ => PyValueError::new_err(err.to_string()),
+    }
+}
+
+type F32Array<'py> = Bound<'py, PyArray1<f32>>;
 
 fn pixel_count(w: usize, h: usize) -> PyResult<usize> {
     w.checked_mul(h)
-        .ok_or_else(|| PyValueError::new_err("image dimensions overflow"))
+        .ok_or_else(|| DimensionOverflowError::new_err("image dimensions overflow"))
+}
+
+fn check_buffer(name: &str, len: usize, expected: usize) -> PyResult<()> {
+    if len != expected {
+        return Err(LengthMismatchError::new_err(format!(
+            "expected {} buffer length {}, got {}",
+            name, expected, len
+        )));
+    }
+    Ok(())
 }
 
+/// Motion is optional: an empty buffer is accepted, otherwise it must hold
+/// one `(dx, dy)` pair per pixel.
+fn check_motion(len: usize, pixels: usize) -> PyResult<()> {
+    if len == 0 {
+        return Ok(());
+    }
+    let expected_motion = pixels.checked_mul(2).ok_or_else(|| {
+        DimensionOverflowError::new_err("pixel count overflow for motion vectors")
+    })?;
+    if len != expected_motion {
+        return Err(LengthMismatchError::new_err(format!(
+            "expected motion buffer length {} or 0, got {}",
+            expected_motion, len
+        )));
+    }
+    Ok(())
+}
+
+/// Borrows a numpy array's data without copying; strided views are refused
+/// rather than silently copied.
+fn contiguous<'a, T: Element, D: Dimension>(
+    array: &'a PyReadonlyArray<'_, T, D>,
+    name: &str,
+) -> PyResult<&'a [T]> {
+    array.as_slice().map_err(|_| {
+        PyValueError::new_err(format!(
+            "{} array must be C-contiguous; pass numpy.ascontiguousarray(...)",
+            name
+        ))
+    })
+}
+
+/// TAA history blend of interleaved frames with `channels` floats per pixel:
+/// 3 for RGB (the default) or 4 for RGBA, where alpha is blended too.
 #[pyfunction]
-fn taa_reproject_py(
-    curr: Vec<f32>,
-    prev: Vec<f32>,
-    motion: Vec<f32>,
+#[pyo3(signature = (curr, prev, motion, w, h, blend, channels = 3))]
+#[allow(clippy::too_many_arguments)]
+fn taa_reproject_py<'py>(
+    py: Python<'py>,
+    curr: PyReadonlyArray1<'py, f32>,
+    prev: PyReadonlyArray1<'py, f32>,
+    motion: PyReadonlyArray1<'py, f32>,
     w: usize,
     h: usize,
     blend: f32,
-) -> PyResult<Vec<f32>> {
+    channels: usize,
+) -> PyResult<F32Array<'py>> {
+    if channels != 3 && channels != 4 {
+        return Err(PyValueError::new_err(format!(
+            "channels must be 3 or 4, got {}",
+            channels
+        )));
+    }
+    let curr = contiguous(&curr, "current")?;
+    let prev = contiguous(&prev, "previous")?;
+    let motion = contiguous(&motion, "motion")?;
+    let pixels = pixel_count(w, h)?;
+    let expected = pixels.checked_mul(channels).ok_or_else(|| {
+        DimensionOverflowError::new_err("pixel count overflow for colour buffers")
+    })?;
+
+    check_buffer("current", curr.len(), expected)?;
+    check_buffer("previous", prev.len(), expected)?;
+    check_motion(motion.len(), pixels)?;
+
+    // Only argument marshalling above needs the GIL; the resolve itself runs
+    // with it released so other Python threads keep going.
+    let out = py.detach(|| {
+        let frame = taa::TaaFrame::new(curr, prev, motion, w, h).with_channels(channels);
+        let params = taa::TaaParams {
+            blend,
+            ..taa::TaaParams::default()
+        };
+        let mut out = vec![0.0_f32; expected];
+        taa::taa_reproject_with(&frame, &params, &mut out, &mut []);
+        out
+    });
+    Ok(out.into_pyarray(py))
+}
+
+/// Runs TAA over an `N x (w*h*3)` stack of frames in one call. Each output
+/// row is the history for the next frame; the first frame passes through, as
+/// with `TaaContext`. `motions` is `N x (w*h*2)`, or `N x 0` for no motion.
+#[pyfunction]
+fn taa_reproject_batch_py<'py>(
+    py: Python<'py>,
+    frames: PyReadonlyArray2<'py, f32>,
+    motions: PyReadonlyArray2<'py, f32>,
+    w: usize,
+    h: usize,
+    blend: f32,
+) -> PyResult<Bound<'py, PyArray2<f32>>> {
+    let [count, frame_len] = [frames.shape()[0], frames.shape()[1]];
+    let motion_len = motions.shape()[1];
+    if motions.shape()[0] != count {
+        return Err(LengthMismatchError::new_err(format!(
+            "expected {} motion rows, got {}",
+            count,
+            motions.shape()[0]
+        )));
+    }
     let pixels = pixel_count(w, h)?;
     let expected_rgb = pixels
         .checked_mul(3)
-        .ok_or_else(|| PyValueError::new_err("pixel count overflow for RGB buffers"))?;
+        .ok_or_else(|| DimensionOverflowError::new_err("pixel count overflow for RGB buffers"))?;
+    check_buffer("frame", frame_len, expected_rgb)?;
+    check_motion(motion_len, pixels)?;
+    let frames = contiguous(&frames, "frames")?;
+    let motions = contiguous(&motions, "motions")?;
 
-    if curr.len() != expected_rgb {
-        return Err(PyValueError::new_err(format!(
-            "expected current buffer length {}, got {}",
-            expected_rgb,
-            curr.len()
-        )));
+    let out = py.detach(|| {
+        let params = taa::TaaParams {
+            blend,
+            ..taa::TaaParams::default()
+        };
+        let mut ctx = taa::TaaContext::new(w, h, params);
+        let mut out = Vec::with_capacity(frames.len());
+        for i in 0..count {
+            let curr = &frames[i * frame_len..(i + 1) * frame_len];
+            let motion = &motions[i * motion_len..(i + 1) * motion_len];
+            out.extend_from_slice(ctx.submit(curr, motion));
+        }
+        out
+    });
+    out.into_pyarray(py).reshape([count, frame_len])
+}
+
+/// Stateful TAA for video streams: keeps the resolved history between calls so
+/// Python only hands over each new frame.
+#[pyclass(name = "TaaContext")]
+struct PyTaaContext {
+    inner: taa::TaaContext,
+    pixels: usize,
+}
+
+#[pymethods]
+impl PyTaaContext {
+    #[new]
+    #[pyo3(signature = (w, h, blend = 0.9))]
+    fn new(w: usize, h: usize, blend: f32) -> PyResult<Self> {
+        let pixels = pixel_count(w, h)?;
+        pixels.checked_mul(3).ok_or_else(|| {
+            DimensionOverflowError::new_err("pixel count overflow for RGB buffers")
+        })?;
+        let params = taa::TaaParams {
+            blend,
+            ..taa::TaaParams::default()
+        };
+        Ok(PyTaaContext {
+            inner: taa::TaaContext::new(w, h, params),
+            pixels,
+        })
     }
-    if prev.len() != expected_rgb {
-        return Err(PyValueError::new_err(format!(
-            "expected previous buffer length {}, got {}",
-            expected_rgb,
-            prev.len()
-        )));
+
+    /// Blends `curr` with the stored history and returns the result, which
+    /// also becomes the history for the next call. The first frame (and the
+    /// first after `reset`) passes through unchanged.
+    fn submit<'py>(
+        &mut self,
+        py: Python<'py>,
+        curr: PyReadonlyArray1<'py, f32>,
+        motion: PyReadonlyArray1<'py, f32>,
+    ) -> PyResult<F32Array<'py>> {
+        let curr = contiguous(&curr, "current")?;
+        let motion = contiguous(&motion, "motion")?;
+        check_buffer("current", curr.len(), self.pixels * 3)?;
+        check_motion(motion.len(), self.pixels)?;
+
+        let inner = &mut self.inner;
+        let out = py.detach(|| inner.submit(curr, motion));
+        Ok(PyArray1::from_slice(py, out))
+    }
+
+    /// Drops the history, e.g. on a scene cut.
+    fn reset(&mut self) {
+        self.inner.reset();
+    }
+
+    #[getter]
+    fn has_history(&self) -> bool {
+        self.inner.has_history()
     }
+}
 
-    if !motion.is_empty() {
-        let expected_motion = pixels
-            .checked_mul(2)
-            .ok_or_else(|| PyValueError::new_err("pixel count overflow for motion vectors"))?;
-        if motion.len() != expected_motion {
+/// Server-side force-directed graph layout, running the same Barnes-Hut engine
+/// as the browser build.
+#[pyclass(name = "PhysicsEngine")]
+#[derive(Default)]
+struct PyPhysicsEngine {
+    engine: Engine,
+}
+
+#[pymethods]
+impl PyPhysicsEngine {
+    #[new]
+    fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replaces the nodes with `positions` (an `N x 3` array or nested list).
+    /// `ids` name the nodes for `set_edges` and default to `"0"`, `"1"`, ...;
+    /// `masses` default to 1.
+    #[pyo3(signature = (positions, ids = None, masses = None))]
+    fn set_nodes(
+        &mut self,
+        positions: PyArrayLike2<'_, f64, AllowTypeChange>,
+        ids: Option<Vec<String>>,
+        masses: Option<PyArrayLike1<'_, f64, AllowTypeChange>>,
+    ) -> PyResult<()> {
+        let shape = positions.shape();
+        if shape[1] != 3 {
             return Err(PyValueError::new_err(format!(
-                "expected motion buffer length {} or 0, got {}",
-                expected_motion,
-                motion.len()
+                "positions must have shape (N, 3), got ({}, {})",
+                shape[0], shape[1]
             )));
         }
+        let count = shape[0];
+        let ids = ids.unwrap_or_else(|| (0..count).map(|i| i.to_string()).collect());
+        check_buffer("ids", ids.len(), count)?;
+        let masses = match &masses {
+            Some(masses) => masses.as_array().to_vec(),
+            None => vec![1.0; count],
+        };
+        check_buffer("masses", masses.len(), count)?;
+
+        let positions = positions.as_array();
+        let nodes = ids
+            .into_iter()
+            .zip(masses)
+            .zip(positions.rows())
+            .map(|((id, mass), p)| Node {
+                id,
+                x: p[0],
+                y: p[1],
+                z: p[2],
+                vx: 0.0,
+                vy: 0.0,
+                vz: 0.0,
+                mass,
+                radius: None,
+            })
+            .collect();
+        self.engine.set_nodes(nodes);
+        Ok(())
+    }
+
+    /// Replaces the edges with `(source_id, target_id)` pairs; `weights`
+    /// default to 1.
+    #[pyo3(signature = (edges, weights = None))]
+    fn set_edges(
+        &mut self,
+        edges: Vec<(String, String)>,
+        weights: Option<Vec<f64>>,
+    ) -> PyResult<()> {
+        let weights = weights.unwrap_or_else(|| vec![1.0; edges.len()]);
+        check_buffer("weights", weights.len(), edges.len())?;
+        let edges = edges
+            .into_iter()
+            .zip(weights)
+            .map(|((source, target), weight)| Edge {
+                source,
+                target,
+                weight,
+            })
+            .collect();
+        self.engine.set_edges(edges);
+        Ok(())
     }
 
-    let mut out = vec![0.0_f32; expected_rgb];
-    taa::taa_reproject(&curr, &prev, &motion, w, h, blend, &mut out);
-    Ok(out)
+    fn set_params(&mut self, repulsion: f64, attraction: f64, damping: f64, theta: f64) {
+        self.engine
+            .set_params(repulsion, attraction, damping, theta);
+    }
+
+    /// Advances the layout by `delta_time` without holding the GIL and
+    /// returns the new `N x 3` positions.
+    fn tick<'py>(
+        &mut self,
+        py: Python<'py>,
+        delta_time: f64,
+    ) -> PyResult<Bound<'py, PyArray2<f64>>> {
+        let engine = &mut self.engine;
+        py.detach(|| engine.tick(delta_time));
+        self.positions(py)
+    }
+
+    /// Current `N x 3` node positions.
+    fn positions<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyArray2<f64>>> {
+        let nodes = self.engine.nodes();
+        let flat: Vec<f64> = nodes.iter().flat_map(|n| [n.x, n.y, n.z]).collect();
+        flat.into_pyarray(py).reshape([nodes.len(), 3])
+    }
 }
 
+/// `max_steps` is the renderer's march budget; the rough boost saturates
+/// when `step_count` reaches it.
 #[pyfunction]
-fn ssr_step_py(hit_depth: f32, roughness: f32, step_count: u32) -> PyResult<(f32, f32)> {
-    Ok(ssr::ssr_step(hit_depth, roughness, step_count))
+#[pyo3(signature = (hit_depth, roughness, step_count, max_steps = ssr::SSR_DEFAULT_MAX_STEPS))]
+fn ssr_step_py(
+    hit_depth: f32,
+    roughness: f32,
+    step_count: u32,
+    max_steps: u32,
+) -> PyResult<(f32, f32)> {
+    Ok(ssr::ssr_step_with_budget(
+        hit_depth, roughness, step_count, max_steps,
+    ))
+}
+
+/// Vectorised `ssr_step_py`: returns `(edge_fade, rough_boost)` arrays with
+/// one entry per input element. Runs without the GIL.
+#[pyfunction]
+fn ssr_step_batch_py<'py>(
+    py: Python<'py>,
+    hit_depth: PyReadonlyArray1<'py, f32>,
+    roughness: PyReadonlyArray1<'py, f32>,
+    step_count: PyReadonlyArray1<'py, u32>,
+) -> PyResult<(F32Array<'py>, F32Array<'py>)> {
+    let hit_depth = contiguous(&hit_depth, "hit depth")?;
+    let roughness = contiguous(&roughness, "roughness")?;
+    let step_count = contiguous(&step_count, "step count")?;
+
+    let (edge, boost) = py
+        .detach(|| {
+            let mut edge = vec![0.0_f32; hit_depth.len()];
+            let mut boost = vec![0.0_f32; hit_depth.len()];
+            ssr::ssr_step_batch_checked(hit_depth, roughness, step_count, &mut edge, &mut boost)
+                .map(|()| (edge, boost))
+        })
+        .map_err(kernel_error)?;
+    Ok((edge.into_pyarray(py), boost.into_pyarray(py)))
 }
 
 #[pyfunction]
@@ -64,10 +388,50 @@ fn interference_py(u: f32, v: f32, t: f32) -> PyResult<f32> {
     Ok(coherence::interference_field(u, v, t))
 }
 
+/// Evaluates `interference_py` over a whole grid without the GIL and returns
+/// it as an `(h, w)` array: `grid[y, x]` is the field at the pixel centre
+/// `u = (x + 0.5) / w`, `v = (y + 0.5) / h`.
+#[pyfunction]
+fn interference_grid_py(
+    py: Python<'_>,
+    w: usize,
+    h: usize,
+    t: f32,
+) -> PyResult<Bound<'_, PyArray2<f32>>> {
+    let pixels = pixel_count(w, h)?;
+    let grid = py
+        .detach(|| {
+            let mut grid = vec![0.0_f32; pixels];
+            coherence::fill_interference_field_checked(w, h, t, &mut grid).map(|()| grid)
+        })
+        .map_err(kernel_error)?;
+    grid.into_pyarray(py).reshape([h, w])
+}
+
+/// Core crate version and enabled features, e.g. `"0.1.0 (std,alloc,rayon)"`.
+#[pyfunction]
+fn version_py() -> String {
+    qce_kernels::kernels_version()
+}
+
 #[pymodule]
-fn qce_kernels_py(_py: Python, m: &PyModule) -> PyResult<()> {
+fn qce_kernels_py(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add(
+        "LengthMismatchError",
+        m.py().get_type::<LengthMismatchError>(),
+    )?;
+    m.add(
+        "DimensionOverflowError",
+        m.py().get_type::<DimensionOverflowError>(),
+    )?;
     m.add_function(wrap_pyfunction!(taa_reproject_py, m)?)?;
+    m.add_function(wrap_pyfunction!(taa_reproject_batch_py, m)?)?;
     m.add_function(wrap_pyfunction!(ssr_step_py, m)?)?;
+    m.add_function(wrap_pyfunction!(ssr_step_batch_py, m)?)?;
     m.add_function(wrap_pyfunction!(interference_py, m)?)?;
+    m.add_function(wrap_pyfunction!(interference_grid_py, m)?)?;
+    m.add_function(wrap_pyfunction!(version_py, m)?)?;
+    m.add_class::<PyTaaContext>()?;
+    m.add_class::<PyPhysicsEngine>()?;
     Ok(())
 }
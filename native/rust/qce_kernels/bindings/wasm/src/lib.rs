@@ -1,8 +1,28 @@
-use js_sys::Array;
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use js_sys::{Array, Float32Array};
 use wasm_bindgen::prelude::*;
+use wasm_bindgen::Clamped;
 
 use qce_kernels::kernels::{coherence, ssr, taa};
+use qce_kernels::KernelError;
 
+/// Core crate version and enabled features, e.g. `"0.1.0 (std,alloc,simd)"`.
+#[wasm_bindgen]
+pub fn version_wasm() -> String {
+    qce_kernels::kernels_version()
+}
+
+/// Float count of a `w * h` frame with `channels` floats per pixel.
+fn frame_len(w: usize, h: usize, channels: usize) -> Result<usize, KernelError> {
+    w.checked_mul(h)
+        .and_then(|pixels| pixels.checked_mul(channels))
+        .ok_or(KernelError::DimensionOverflow)
+}
+
+/// TAA history blend of RGB frames. Mismatched buffers or overflowing
+/// dimensions throw a JS `Error` instead of trapping the module.
 #[wasm_bindgen]
 pub fn taa_reproject_wasm(
     curr: &[f32],
@@ -11,28 +31,307 @@ pub fn taa_reproject_wasm(
     w: usize,
     h: usize,
     blend: f32,
-) -> Vec<f32> {
-    let pixels = w
-        .checked_mul(h)
-        .expect("image dimensions overflow when computing pixel count");
-    let total = pixels
-        .checked_mul(3)
-        .expect("pixel count overflow when computing RGB buffer length");
-    let mut out = vec![0.0_f32; total];
-    taa::taa_reproject(curr, prev, motion, w, h, blend, &mut out);
-    out
+) -> Result<Vec<f32>, JsError> {
+    let mut out = vec![0.0_f32; frame_len(w, h, 3)?];
+    taa::taa_reproject_checked(curr, prev, motion, w, h, blend, &mut out)?;
+    Ok(out)
+}
+
+thread_local! {
+    /// Output of the last [`taa_reproject_view_wasm`] call, kept alive so the
+    /// returned view stays valid.
+    static TAA_VIEW_OUT: RefCell<Vec<f32>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Like [`taa_reproject_wasm`], but returns a `Float32Array` view straight
+/// over wasm memory instead of copying the result into a new JS array.
+///
+/// The view aliases a buffer owned by the module: it is overwritten by the
+/// next call and detached if wasm memory grows, so upload or copy it before
+/// calling into the module again.
+#[wasm_bindgen]
+pub fn taa_reproject_view_wasm(
+    curr: &[f32],
+    prev: &[f32],
+    motion: &[f32],
+    w: usize,
+    h: usize,
+    blend: f32,
+) -> Result<Float32Array, JsError> {
+    let total = frame_len(w, h, 3)?;
+    TAA_VIEW_OUT.with(|out| {
+        let mut out = out.borrow_mut();
+        out.resize(total, 0.0);
+        taa::taa_reproject_checked(curr, prev, motion, w, h, blend, &mut out)?;
+        // SAFETY: the buffer lives in a thread-local that is only touched by
+        // this function; the contract above tells callers the view is
+        // invalidated by the next call or by memory growth.
+        Ok(unsafe { Float32Array::view(&out) })
+    })
+}
+
+thread_local! {
+    /// Buffers handed out by [`alloc_f32`], keyed by address, so the
+    /// pointer-based entry points only ever touch memory the module owns.
+    static ALLOCATIONS: RefCell<HashMap<usize, Box<[f32]>>> = RefCell::new(HashMap::new());
+}
+
+/// Allocates `len` zeroed floats in wasm memory for the pointer-based entry
+/// points and returns their address (null for `len == 0`). JS views them with
+/// `new Float32Array(memory.buffer, ptr, len)`; release them with
+/// [`free_f32`].
+#[wasm_bindgen]
+pub fn alloc_f32(len: usize) -> *mut f32 {
+    if len == 0 {
+        return std::ptr::null_mut();
+    }
+    let mut buffer = vec![0.0_f32; len].into_boxed_slice();
+    let ptr = buffer.as_mut_ptr();
+    ALLOCATIONS.with(|allocations| allocations.borrow_mut().insert(ptr as usize, buffer));
+    ptr
+}
+
+/// Frees a buffer returned by [`alloc_f32`] with the same `len`. Throws if
+/// `ptr` is not a live allocation of that length.
+#[wasm_bindgen]
+pub fn free_f32(ptr: *mut f32, len: usize) -> Result<(), JsError> {
+    if len == 0 && ptr.is_null() {
+        return Ok(());
+    }
+    ALLOCATIONS.with(|allocations| {
+        let mut allocations = allocations.borrow_mut();
+        registered(&allocations, "freed", ptr, len)?;
+        allocations.remove(&(ptr as usize));
+        Ok(())
+    })
+}
+
+/// Zero-copy [`taa_reproject_wasm`] over buffers from [`alloc_f32`] that JS
+/// fills through `Float32Array` views. `curr`, `prev` and `out` each hold
+/// `w * h * 3` floats; `motion` holds `motion_len` floats (`0` or
+/// `w * h * 2`). Throws if a pointer is not a live allocation of its stated
+/// length, or if `out` is also an input.
+#[wasm_bindgen]
+#[allow(clippy::too_many_arguments)]
+pub fn taa_reproject_ptr_wasm(
+    curr: *const f32,
+    prev: *const f32,
+    motion: *const f32,
+    motion_len: usize,
+    out: *mut f32,
+    w: usize,
+    h: usize,
+    blend: f32,
+) -> Result<(), JsError> {
+    let len = frame_len(w, h, 3)?;
+    let aliases = |ptr: *const f32, n: usize| n != 0 && len != 0 && std::ptr::eq(ptr, out);
+    if aliases(curr, len) || aliases(prev, len) || aliases(motion, motion_len) {
+        return Err(KernelError::InvalidArgument("output buffer overlaps an input").into());
+    }
+    ALLOCATIONS.with(|allocations| {
+        let mut allocations = allocations.borrow_mut();
+        registered(&allocations, "output", out, len)?;
+        // Take the output out of the registry so it can be borrowed mutably
+        // alongside the inputs, and put it back whatever the outcome.
+        let mut out_buffer = match len {
+            0 => Box::default(),
+            _ => allocations.remove(&(out as usize)).unwrap_or_default(),
+        };
+        let result = (|| {
+            let curr = registered(&allocations, "current", curr, len)?;
+            let prev = registered(&allocations, "previous", prev, len)?;
+            let motion = registered(&allocations, "motion", motion, motion_len)?;
+            taa::taa_reproject_checked(curr, prev, motion, w, h, blend, &mut out_buffer)
+        })();
+        if len != 0 {
+            allocations.insert(out as usize, out_buffer);
+        }
+        Ok(result?)
+    })
+}
+
+/// The live [`alloc_f32`] buffer at `ptr`, which must hold exactly `len`
+/// floats; empty for `len == 0` whatever `ptr` is.
+fn registered<'a>(
+    allocations: &'a HashMap<usize, Box<[f32]>>,
+    buffer: &'static str,
+    ptr: *const f32,
+    len: usize,
+) -> Result<&'a [f32], KernelError> {
+    if len == 0 {
+        return Ok(&[]);
+    }
+    let allocation = allocations
+        .get(&(ptr as usize))
+        .ok_or(KernelError::InvalidArgument(
+            "pointer was not returned by alloc_f32 or was already freed",
+        ))?;
+    if allocation.len() != len {
+        return Err(KernelError::LengthMismatch {
+            buffer,
+            len: allocation.len(),
+            expected: len,
+        });
+    }
+    Ok(allocation)
+}
+
+/// Per-stream TAA state owning a persistent output buffer, so a 60fps loop
+/// does not allocate a fresh `Vec` every frame.
+#[wasm_bindgen]
+pub struct TaaBuffers {
+    width: usize,
+    height: usize,
+    channels: usize,
+    out: Vec<f32>,
 }
 
 #[wasm_bindgen]
-pub fn ssr_step_wasm(hit_depth: f32, roughness: f32, step_count: u32) -> Array {
-    let (edge, boost) = ssr::ssr_step(hit_depth, roughness, step_count);
+impl TaaBuffers {
+    /// Buffers for `w * h` frames of `channels` (3 or 4) floats per pixel.
+    #[wasm_bindgen(constructor)]
+    pub fn new(w: usize, h: usize, channels: usize) -> Result<TaaBuffers, JsError> {
+        if channels != 3 && channels != 4 {
+            return Err(KernelError::InvalidArgument("channels must be 3 or 4").into());
+        }
+        let mut buffers = TaaBuffers {
+            width: 0,
+            height: 0,
+            channels,
+            out: Vec::new(),
+        };
+        buffers.resize(w, h)?;
+        Ok(buffers)
+    }
+
+    /// Changes the frame size. The output buffer is only reallocated when the
+    /// dimensions actually change.
+    pub fn resize(&mut self, w: usize, h: usize) -> Result<(), JsError> {
+        if (w, h) == (self.width, self.height) {
+            return Ok(());
+        }
+        let total = frame_len(w, h, self.channels)?;
+        self.width = w;
+        self.height = h;
+        self.out = vec![0.0; total];
+        Ok(())
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn channels(&self) -> usize {
+        self.channels
+    }
+
+    /// Resolves `curr` against `prev` into the owned buffer and returns a view
+    /// over it. The view is valid until the next `process`/`resize` call or
+    /// until wasm memory grows.
+    pub fn process(
+        &mut self,
+        curr: &[f32],
+        prev: &[f32],
+        motion: &[f32],
+        blend: f32,
+    ) -> Result<Float32Array, JsError> {
+        let frame = taa::TaaFrame::new(curr, prev, motion, self.width, self.height)
+            .with_channels(self.channels);
+        let params = taa::TaaParams {
+            blend,
+            ..taa::TaaParams::default()
+        };
+        taa::taa_reproject_with_checked(&frame, &params, &mut self.out, &mut [])?;
+        Ok(self.output())
+    }
+
+    /// View over the most recent output.
+    pub fn output(&self) -> Float32Array {
+        // SAFETY: `out` is only replaced by `resize` and written by `process`;
+        // both invalidate earlier views, as documented.
+        unsafe { Float32Array::view(&self.out) }
+    }
+}
+
+/// `max_steps` is the renderer's march budget (default 32); the rough boost
+/// saturates when `step_count` reaches it.
+#[wasm_bindgen]
+pub fn ssr_step_wasm(
+    hit_depth: f32,
+    roughness: f32,
+    step_count: u32,
+    max_steps: Option<u32>,
+) -> Array {
+    let max_steps = max_steps.unwrap_or(ssr::SSR_DEFAULT_MAX_STEPS);
+    let (edge, boost) = ssr::ssr_step_with_budget(hit_depth, roughness, step_count, max_steps);
     let arr = Array::new();
     arr.push(&JsValue::from(edge));
     arr.push(&JsValue::from(boost));
     arr
 }
 
+/// Batched [`ssr_step_wasm`]: returns interleaved `[edge0, boost0, edge1,
+/// boost1, ...]` for element-wise inputs. Throws if the lengths differ.
+#[wasm_bindgen]
+pub fn ssr_step_batch_wasm(
+    hit_depth: &[f32],
+    roughness: &[f32],
+    step_count: &[u32],
+) -> Result<Vec<f32>, JsError> {
+    let mut edge = vec![0.0_f32; hit_depth.len()];
+    let mut boost = vec![0.0_f32; hit_depth.len()];
+    ssr::ssr_step_batch_checked(hit_depth, roughness, step_count, &mut edge, &mut boost)?;
+    Ok(edge
+        .into_iter()
+        .zip(boost)
+        .flat_map(|(e, b)| [e, b])
+        .collect())
+}
+
 #[wasm_bindgen]
 pub fn interference_wasm(u: f32, v: f32, t: f32) -> f32 {
     coherence::interference_field(u, v, t)
 }
+
+/// Evaluates the interference field at each `(us[i], vs[i])` in one call,
+/// avoiding a JS/wasm crossing per point. Throws if the arrays differ in
+/// length.
+#[wasm_bindgen]
+pub fn interference_batch_wasm(us: &[f32], vs: &[f32], t: f32) -> Result<Vec<f32>, JsError> {
+    if vs.len() != us.len() {
+        return Err(KernelError::LengthMismatch {
+            buffer: "vs",
+            len: vs.len(),
+            expected: us.len(),
+        }
+        .into());
+    }
+    Ok(us
+        .iter()
+        .zip(vs)
+        .map(|(&u, &v)| coherence::interference_field(u, v, t))
+        .collect())
+}
+
+/// Renders the interference field at time `t` as greyscale RGBA bytes ready
+/// for `new ImageData(pixels, w, h)`: `[-1, 1]` maps to `[0, 255]` and alpha
+/// is opaque. Pixel `(x, y)` samples the field at its centre, as in
+/// `fill_interference_field`.
+#[wasm_bindgen]
+pub fn interference_image_wasm(w: usize, h: usize, t: f32) -> Result<Clamped<Vec<u8>>, JsError> {
+    let mut field = vec![0.0_f32; frame_len(w, h, 1)?];
+    coherence::fill_interference_field_checked(w, h, t, &mut field)?;
+    let mut pixels = Vec::with_capacity(frame_len(w, h, 4)?);
+    for value in field {
+        let grey = ((value * 0.5 + 0.5) * 255.0).round().clamp(0.0, 255.0) as u8;
+        pixels.extend_from_slice(&[grey, grey, grey, 255]);
+    }
+    Ok(Clamped(pixels))
+}
@@ -0,0 +1,69 @@
+use core::fmt;
+
+/// Error returned by the fallible (`*_checked`) kernel entry points instead of
+/// panicking on bad input.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KernelError {
+    /// A buffer's length does not match the one implied by the dimensions.
+    LengthMismatch {
+        buffer: &'static str,
+        len: usize,
+        expected: usize,
+    },
+    /// The dimensions are too large to compute a buffer length.
+    DimensionOverflow,
+    /// A parameter that must be finite is NaN or infinite.
+    NonFinite { name: &'static str },
+    /// The inputs are inconsistent in some other way, described by the message.
+    InvalidArgument(&'static str),
+}
+
+impl fmt::Display for KernelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KernelError::LengthMismatch {
+                buffer,
+                len,
+                expected,
+            } => write!(
+                f,
+                "{} buffer length {} does not match expected {}",
+                buffer, len, expected
+            ),
+            KernelError::DimensionOverflow => {
+                write!(f, "image dimensions overflow when computing buffer length")
+            }
+            KernelError::NonFinite { name } => write!(f, "{} must be finite", name),
+            KernelError::InvalidArgument(message) => f.write_str(message),
+        }
+    }
+}
+
+impl core::error::Error for KernelError {}
+
+/// `Ok` when `len == expected`, otherwise a [`KernelError::LengthMismatch`]
+/// naming `buffer`.
+pub(crate) fn check_len(
+    buffer: &'static str,
+    len: usize,
+    expected: usize,
+) -> Result<(), KernelError> {
+    if len == expected {
+        Ok(())
+    } else {
+        Err(KernelError::LengthMismatch {
+            buffer,
+            len,
+            expected,
+        })
+    }
+}
+
+/// `Ok` when `value` is finite, otherwise a [`KernelError::NonFinite`].
+pub(crate) fn check_finite(name: &'static str, value: f32) -> Result<(), KernelError> {
+    if value.is_finite() {
+        Ok(())
+    } else {
+        Err(KernelError::NonFinite { name })
+    }
+}
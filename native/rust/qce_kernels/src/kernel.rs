@@ -0,0 +1,11 @@
+//! Common interface over the image kernels, so a pipeline can chain them
+//! without knowing which one it is calling.
+
+/// A kernel that turns `input` into `out` under some `Params`. Like the
+/// kernels' free functions, implementations panic when the buffer lengths do
+/// not match what they were configured for.
+pub trait Kernel {
+    type Params;
+
+    fn apply(&self, input: &[f32], params: &Self::Params, out: &mut [f32]);
+}
@@ -1,7 +1,559 @@
+use core::f32::consts::TAU;
+
+use crate::error::{check_finite, check_len, KernelError};
+use crate::kernel::Kernel;
+#[cfg(all(not(feature = "std"), not(test)))]
+use crate::math::F32Ext;
+use crate::utils;
+
+/// One sine wave of the interference pattern. Its phase at `(u, v, t)` is
+/// `(u * fx + v * fy) * TAU * frequency + t * time_rate + phase`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct InterferenceWave {
+    pub fx: f32,
+    pub fy: f32,
+    pub frequency: f32,
+    pub phase: f32,
+    pub time_rate: f32,
+    pub amplitude: f32,
+}
+
+/// Waves summed by [`interference_field_cfg`]. The default reproduces
+/// [`interference_field`].
+///
+/// `transform` maps `(u, v)` to `(t[0][0] * u + t[0][1] * v, t[1][0] * u +
+/// t[1][1] * v)` before the waves are evaluated, stretching or rotating the
+/// whole pattern; see [`with_anisotropy`](Self::with_anisotropy). The
+/// default is the identity.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct InterferenceParams {
+    pub waves: [InterferenceWave; 3],
+    pub transform: [[f32; 2]; 2],
+}
+
+impl Default for InterferenceParams {
+    fn default() -> Self {
+        let wave = |fx, fy, frequency, phase, time_rate| InterferenceWave {
+            fx,
+            fy,
+            frequency,
+            phase,
+            time_rate,
+            amplitude: 1.0,
+        };
+        InterferenceParams {
+            waves: [
+                wave(0.9, 0.2, 0.75, 0.0, 0.07),
+                wave(-0.3, 1.0, 0.95, 1.7, 0.05),
+                wave(0.2, -1.0, 0.60, 3.4, 0.09),
+            ],
+            transform: [[1.0, 0.0], [0.0, 1.0]],
+        }
+    }
+}
+
+impl InterferenceParams {
+    /// A deterministic variation of the default waves: each wave's direction
+    /// is rotated, its frequency scaled by up to ±20% and its phase shifted,
+    /// all derived from `seed`. The same seed always gives the same field.
+    pub fn seeded(seed: u32) -> Self {
+        let mut params = InterferenceParams::default();
+        let mut state = seed;
+        let mut next = || {
+            state = utils::rng::hash_u32(state);
+            state as f32 / u32::MAX as f32
+        };
+        for wave in &mut params.waves {
+            let (sin, cos) = (next() * TAU).sin_cos();
+            (wave.fx, wave.fy) = (wave.fx * cos - wave.fy * sin, wave.fx * sin + wave.fy * cos);
+            wave.frequency *= 0.8 + 0.4 * next();
+            wave.phase += next() * TAU;
+        }
+        params
+    }
+
+    /// These waves with the pattern stretched `scale_x` times along an axis
+    /// at `rotation` radians from `u` and `scale_y` times along the
+    /// perpendicular one, so `scale_x > scale_y` draws streaks along that
+    /// axis. Replaces any previous `transform`.
+    pub fn with_anisotropy(&self, scale_x: f32, scale_y: f32, rotation: f32) -> Self {
+        assert!(
+            scale_x > 0.0 && scale_x.is_finite() && scale_y > 0.0 && scale_y.is_finite(),
+            "anisotropy scales {} and {} must be positive and finite",
+            scale_x,
+            scale_y
+        );
+        let (sin, cos) = rotation.sin_cos();
+        InterferenceParams {
+            transform: [
+                [cos / scale_x, sin / scale_x],
+                [-sin / scale_y, cos / scale_y],
+            ],
+            ..*self
+        }
+    }
+
+    /// `(u, v)` mapped through `transform`.
+    #[inline]
+    fn transform_uv(&self, u: f32, v: f32) -> (f32, f32) {
+        let m = &self.transform;
+        (m[0][0] * u + m[0][1] * v, m[1][0] * u + m[1][1] * v)
+    }
+
+    /// These waves with their spatial frequencies rounded to whole cycles per
+    /// `period`, so the field repeats exactly every `period` in `u` and `v`.
+    /// A wave that would round to no cycles at all keeps one along its
+    /// dominant axis. The repeat is in transformed coordinates, so it only
+    /// lines up with `(u, v)` for the identity `transform`.
+    pub fn tiled(&self, period: f32) -> Self {
+        assert!(
+            period > 0.0 && period.is_finite(),
+            "tiling period {} must be positive and finite",
+            period
+        );
+        let mut params = *self;
+        for wave in &mut params.waves {
+            let mut kx = (wave.fx * wave.frequency * period).round();
+            let mut ky = (wave.fy * wave.frequency * period).round();
+            if kx == 0.0 && ky == 0.0 {
+                if wave.fx.abs() >= wave.fy.abs() {
+                    kx = 1.0_f32.copysign(wave.fx);
+                } else {
+                    ky = 1.0_f32.copysign(wave.fy);
+                }
+            }
+            wave.fx = kx / period;
+            wave.fy = ky / period;
+            wave.frequency = 1.0;
+        }
+        params
+    }
+}
+
 /// Generates a stable interference field useful for procedural shading.
 pub fn interference_field(u: f32, v: f32, t: f32) -> f32 {
-    let w1 = ((u * 0.9 + v * 0.2) * core::f32::consts::TAU * 0.75 + t * 0.07).sin();
-    let w2 = ((-u * 0.3 + v * 1.0) * core::f32::consts::TAU * 0.95 + t * 0.05 + 1.7).sin();
-    let w3 = ((u * 0.2 - v * 1.0) * core::f32::consts::TAU * 0.60 + t * 0.09 + 3.4).sin();
-    (w1 + w2 + w3) / 3.0
+    interference_field_cfg(u, v, t, &InterferenceParams::default())
+}
+
+/// [`interference_field`] with waves varied by `seed`; see
+/// [`InterferenceParams::seeded`].
+pub fn interference_field_seeded(u: f32, v: f32, t: f32, seed: u32) -> f32 {
+    interference_field_cfg(u, v, t, &InterferenceParams::seeded(seed))
+}
+
+/// [`interference_field`] made seamlessly tileable over `period` in both `u`
+/// and `v`; see [`InterferenceParams::tiled`].
+pub fn interference_field_tiled(u: f32, v: f32, t: f32, period: f32) -> f32 {
+    interference_field_cfg(u, v, t, &InterferenceParams::default().tiled(period))
+}
+
+/// [`interference_field`] animated on a seamless loop of `loop_len` time
+/// units. Instead of drifting linearly, each wave's temporal phase follows
+/// `r * sin(2π t / loop_len + phase)` with `r` chosen so it moves at the
+/// wave's `time_rate`, so `t = 0` and `t = loop_len` give identical fields.
+/// `loop_len` must be positive and finite; otherwise there is no loop and
+/// the plain [`interference_field`] is returned.
+pub fn interference_field_loop(u: f32, v: f32, t: f32, loop_len: f32) -> f32 {
+    if !(loop_len.is_finite() && loop_len > 0.0) {
+        return interference_field(u, v, t);
+    }
+    let theta = TAU * (t / loop_len).fract();
+    let mut sum = 0.0_f32;
+    let mut total = 0.0_f32;
+    for wave in &InterferenceParams::default().waves {
+        let radius = wave.time_rate * loop_len / TAU;
+        let angle = (u * wave.fx + v * wave.fy) * TAU * wave.frequency
+            + radius * (theta + wave.phase).sin();
+        sum += (angle + wave.phase).sin() * wave.amplitude;
+        total += wave.amplitude.abs();
+    }
+    if total > 0.0 {
+        sum / total
+    } else {
+        0.0
+    }
+}
+
+/// Domain-warped [`interference_field`]: two offset evaluations of the field
+/// displace `(u, v)` by up to `warp_strength` before the final sample, giving
+/// flowing, organic shapes. A strength of `0` reproduces the plain field.
+pub fn interference_warped(u: f32, v: f32, t: f32, warp_strength: f32) -> f32 {
+    let du = interference_field(u + 5.2, v + 1.3, t);
+    let dv = interference_field(u + 1.7, v + 9.2, t);
+    interference_field(u + warp_strength * du, v + warp_strength * dv, t)
+}
+
+/// Three decorrelated interference channels for colourful patterns. Each
+/// channel shifts every wave's phase by a third of a turn and raises its
+/// frequency by 10% relative to the previous channel. All channels lie in
+/// `[-1, 1]`.
+pub fn interference_rgb(u: f32, v: f32, t: f32) -> (f32, f32, f32) {
+    let channel = |c: usize| {
+        let mut cfg = InterferenceParams::default();
+        for wave in &mut cfg.waves {
+            wave.phase += c as f32 * TAU / 3.0;
+            wave.frequency *= 1.0 + 0.1 * c as f32;
+        }
+        interference_field_cfg(u, v, t, &cfg)
+    };
+    (channel(0), channel(1), channel(2))
+}
+
+/// Interference field built from `cfg`'s waves. The result is the
+/// amplitude-weighted mean of the waves, so it stays within `[-1, 1]`.
+pub fn interference_field_cfg(u: f32, v: f32, t: f32, cfg: &InterferenceParams) -> f32 {
+    let (u, v) = cfg.transform_uv(u, v);
+    let mut sum = 0.0_f32;
+    let mut total = 0.0_f32;
+    for wave in &cfg.waves {
+        let angle = (u * wave.fx + v * wave.fy) * TAU * wave.frequency + t * wave.time_rate;
+        sum += (angle + wave.phase).sin() * wave.amplitude;
+        total += wave.amplitude.abs();
+    }
+    if total > 0.0 {
+        sum / total
+    } else {
+        0.0
+    }
+}
+
+/// [`interference_field`] with time in `f64`, for animations that run long
+/// enough for an `f32` clock to lose precision (around `t = 1e5` the `f32`
+/// field visibly steps). Each wave's temporal phase is reduced modulo one turn
+/// in `f64` before it is narrowed, so the result stays smooth at any `t`.
+pub fn interference_field_t64(u: f32, v: f32, t: f64) -> f32 {
+    let mut sum = 0.0_f32;
+    let mut total = 0.0_f32;
+    for wave in &InterferenceParams::default().waves {
+        let turns = (t * wave.time_rate as f64 + wave.phase as f64) / core::f64::consts::TAU;
+        // Cast rather than `fract` so this also builds without `std`.
+        let time_phase = (turns - turns as i64 as f64) as f32 * TAU;
+        let angle = (u * wave.fx + v * wave.fy) * TAU * wave.frequency + time_phase;
+        sum += angle.sin() * wave.amplitude;
+        total += wave.amplitude.abs();
+    }
+    if total > 0.0 {
+        sum / total
+    } else {
+        0.0
+    }
+}
+
+/// [`interference_field`] remapped from `[-1, 1]` to `[0, 1]`, then shaped
+/// with [`utils::remap_contrast_bias`].
+pub fn interference_remapped(u: f32, v: f32, t: f32, contrast: f32, bias: f32) -> f32 {
+    let x = interference_field(u, v, t) * 0.5 + 0.5;
+    utils::remap_contrast_bias(x, contrast, bias)
+}
+
+/// Evaluates [`interference_field`] over a `w * h` grid covering `[0, 1]²`,
+/// sampling pixel centres (`u = (x + 0.5) / w`), and writes the values row by
+/// row into `out`. Rows are filled in parallel when `rayon` is enabled.
+pub fn fill_interference_field(w: usize, h: usize, t: f32, out: &mut [f32]) {
+    if let Err(err) = fill_interference_field_checked(w, h, t, out) {
+        panic!("{}", err);
+    }
+}
+
+/// Fallible [`fill_interference_field`]: returns an error instead of
+/// panicking when `out` does not hold `w * h` values.
+pub fn fill_interference_field_checked(
+    w: usize,
+    h: usize,
+    t: f32,
+    out: &mut [f32],
+) -> Result<(), KernelError> {
+    let pixel_count = w.checked_mul(h).ok_or(KernelError::DimensionOverflow)?;
+    check_len("output", out.len(), pixel_count)?;
+    if pixel_count == 0 {
+        return Ok(());
+    }
+
+    let fill_row = |y: usize, row: &mut [f32]| {
+        fill_interference_row(row, w, (y as f32 + 0.5) / h as f32, t);
+    };
+
+    #[cfg(all(feature = "rayon", not(target_arch = "wasm32")))]
+    {
+        use rayon::prelude::*;
+        crate::pool::install(|| {
+            out.par_chunks_mut(w)
+                .enumerate()
+                .for_each(|(y, row)| fill_row(y, row));
+        });
+    }
+
+    #[cfg(not(all(feature = "rayon", not(target_arch = "wasm32"))))]
+    for (y, row) in out.chunks_mut(w).enumerate() {
+        fill_row(y, row);
+    }
+    Ok(())
+}
+
+/// Bakes [`interference_field`] as a height map into a tangent-space normal
+/// map: pixel `(x, y)` (sampled at pixel centres, as for
+/// [`fill_interference_field`]) gets the normal
+/// `normalize(-strength * d/du, -strength * d/dv, 1)` from
+/// [`interference_field_grad`], written as three floats encoded into
+/// `[0, 1]` as `n * 0.5 + 0.5`. A flat field encodes to `(0.5, 0.5, 1.0)`.
+pub fn interference_normal_map(w: usize, h: usize, t: f32, strength: f32, out: &mut [f32]) {
+    if let Err(err) = interference_normal_map_checked(w, h, t, strength, out) {
+        panic!("{}", err);
+    }
+}
+
+/// Fallible [`interference_normal_map`]: returns an error instead of
+/// panicking when `out` does not hold `w * h * 3` values or `strength` is not
+/// finite.
+pub fn interference_normal_map_checked(
+    w: usize,
+    h: usize,
+    t: f32,
+    strength: f32,
+    out: &mut [f32],
+) -> Result<(), KernelError> {
+    let len = w
+        .checked_mul(h)
+        .and_then(|pixels| pixels.checked_mul(3))
+        .ok_or(KernelError::DimensionOverflow)?;
+    check_len("output", out.len(), len)?;
+    check_finite("strength", strength)?;
+
+    for (idx, normal) in out.chunks_exact_mut(3).enumerate() {
+        let u = ((idx % w) as f32 + 0.5) / w as f32;
+        let v = ((idx / w) as f32 + 0.5) / h as f32;
+        let (du, dv) = interference_field_grad(u, v, t);
+        let (nx, ny) = (-strength * du, -strength * dv);
+        let inv_len = 1.0 / (nx * nx + ny * ny + 1.0).sqrt();
+        normal[0] = nx * inv_len * 0.5 + 0.5;
+        normal[1] = ny * inv_len * 0.5 + 0.5;
+        normal[2] = inv_len * 0.5 + 0.5;
+    }
+    Ok(())
+}
+
+/// Fills a texture atlas of `cols * rows` tiles, each `tile_w * tile_h`
+/// pixels, laid out row-major in an `(cols * tile_w) * (rows * tile_h)`
+/// buffer. Tile `i` (counted along rows of tiles) uses the waves of
+/// [`InterferenceParams::seeded`]`(seeds[i])` tiled to a period of one tile,
+/// so every tile wraps seamlessly onto itself while differently seeded tiles
+/// differ.
+#[allow(clippy::too_many_arguments)]
+pub fn fill_interference_atlas(
+    tile_w: usize,
+    tile_h: usize,
+    cols: usize,
+    rows: usize,
+    t: f32,
+    seeds: &[u32],
+    out: &mut [f32],
+) {
+    if let Err(err) = fill_interference_atlas_checked(tile_w, tile_h, cols, rows, t, seeds, out) {
+        panic!("{}", err);
+    }
+}
+
+/// Fallible [`fill_interference_atlas`]: returns an error instead of
+/// panicking when `seeds` does not hold `cols * rows` values or `out` the
+/// whole atlas.
+#[allow(clippy::too_many_arguments)]
+pub fn fill_interference_atlas_checked(
+    tile_w: usize,
+    tile_h: usize,
+    cols: usize,
+    rows: usize,
+    t: f32,
+    seeds: &[u32],
+    out: &mut [f32],
+) -> Result<(), KernelError> {
+    let tiles = cols
+        .checked_mul(rows)
+        .ok_or(KernelError::DimensionOverflow)?;
+    let atlas_w = tile_w
+        .checked_mul(cols)
+        .ok_or(KernelError::DimensionOverflow)?;
+    let pixel_count = tile_h
+        .checked_mul(rows)
+        .and_then(|atlas_h| atlas_h.checked_mul(atlas_w))
+        .ok_or(KernelError::DimensionOverflow)?;
+    check_len("seeds", seeds.len(), tiles)?;
+    check_len("output", out.len(), pixel_count)?;
+    if pixel_count == 0 {
+        return Ok(());
+    }
+
+    for (tile, &seed) in seeds.iter().enumerate() {
+        let params = InterferenceParams::seeded(seed).tiled(1.0);
+        let (col, row) = (tile % cols, tile / cols);
+        for y in 0..tile_h {
+            let v = (y as f32 + 0.5) / tile_h as f32;
+            let start = (row * tile_h + y) * atlas_w + col * tile_w;
+            for (x, value) in out[start..start + tile_w].iter_mut().enumerate() {
+                let u = (x as f32 + 0.5) / tile_w as f32;
+                *value = interference_field_cfg(u, v, t, &params);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// [`fill_interference_field`] as a [`Kernel`] whose parameter is the time
+/// `t`. It generates rather than filters, so the input is ignored.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InterferenceKernel {
+    pub width: usize,
+    pub height: usize,
+}
+
+impl InterferenceKernel {
+    pub fn new(width: usize, height: usize) -> Self {
+        InterferenceKernel { width, height }
+    }
+}
+
+impl Kernel for InterferenceKernel {
+    type Params = f32;
+
+    fn apply(&self, _input: &[f32], t: &f32, out: &mut [f32]) {
+        fill_interference_field(self.width, self.height, *t, out);
+    }
+}
+
+/// One row of [`fill_interference_field`] at height `v`.
+#[cfg(not(feature = "simd"))]
+fn fill_interference_row(row: &mut [f32], w: usize, v: f32, t: f32) {
+    for (x, value) in row.iter_mut().enumerate() {
+        *value = interference_field((x as f32 + 0.5) / w as f32, v, t);
+    }
+}
+
+/// One row of [`fill_interference_field`] at height `v`, eight pixels at a
+/// time with a scalar tail. `wide` maps the lanes to SSE/AVX, NEON or wasm
+/// `simd128` as available. Its polynomial sine differs from the scalar one by
+/// a few ULP.
+#[cfg(feature = "simd")]
+fn fill_interference_row(row: &mut [f32], w: usize, v: f32, t: f32) {
+    use wide::f32x8;
+
+    let cfg = InterferenceParams::default();
+    let total: f32 = cfg.waves.iter().map(|wave| wave.amplitude.abs()).sum();
+    let lanes = f32x8::from([0.5, 1.5, 2.5, 3.5, 4.5, 5.5, 6.5, 7.5]);
+    let width = f32x8::splat(w as f32);
+
+    let start = row.len() - row.len() % 8;
+    for (i, chunk) in row.chunks_exact_mut(8).enumerate() {
+        let u = (f32x8::splat((i * 8) as f32) + lanes) / width;
+        let mut sum = f32x8::ZERO;
+        for wave in &cfg.waves {
+            let angle = (u * wave.fx + f32x8::splat(v * wave.fy)) * (TAU * wave.frequency)
+                + f32x8::splat(t * wave.time_rate + wave.phase);
+            sum += angle.sin() * wave.amplitude;
+        }
+        let value = if total > 0.0 {
+            sum / total
+        } else {
+            f32x8::ZERO
+        };
+        chunk.copy_from_slice(&value.to_array());
+    }
+
+    for (x, value) in row.iter_mut().enumerate().skip(start) {
+        *value = interference_field((x as f32 + 0.5) / w as f32, v, t);
+    }
+}
+
+/// Analytic gradient `(d/du, d/dv)` of [`interference_field`].
+pub fn interference_field_grad(u: f32, v: f32, t: f32) -> (f32, f32) {
+    interference_field_grad_cfg(u, v, t, &InterferenceParams::default())
+}
+
+/// Analytic gradient `(d/du, d/dv)` of [`interference_field_cfg`], from the
+/// cosine derivative of each wave.
+pub fn interference_field_grad_cfg(u: f32, v: f32, t: f32, cfg: &InterferenceParams) -> (f32, f32) {
+    let (u, v) = cfg.transform_uv(u, v);
+    let mut du = 0.0_f32;
+    let mut dv = 0.0_f32;
+    let mut total = 0.0_f32;
+    for wave in &cfg.waves {
+        let angle = (u * wave.fx + v * wave.fy) * TAU * wave.frequency + t * wave.time_rate;
+        let slope = (angle + wave.phase).cos() * wave.amplitude * TAU * wave.frequency;
+        du += slope * wave.fx;
+        dv += slope * wave.fy;
+        total += wave.amplitude.abs();
+    }
+    if total > 0.0 {
+        // Chain rule through the transform.
+        let m = &cfg.transform;
+        (
+            (m[0][0] * du + m[1][0] * dv) / total,
+            (m[0][1] * du + m[1][1] * dv) / total,
+        )
+    } else {
+        (0.0, 0.0)
+    }
+}
+
+/// Fractal sum of `octaves` copies of [`interference_field`], each
+/// `lacunarity` times higher in frequency and `gain` times weaker than the
+/// last, normalised by the total amplitude so it stays within `[-1, 1]`.
+pub fn interference_fbm(u: f32, v: f32, t: f32, octaves: u32, lacunarity: f32, gain: f32) -> f32 {
+    sum_octaves(u, v, t, octaves, lacunarity, gain, |x| x)
+}
+
+/// Turbulence: [`interference_fbm`] over the absolute value of each octave,
+/// giving creased, marbled patterns in `[0, 1]`.
+pub fn interference_turbulence(
+    u: f32,
+    v: f32,
+    t: f32,
+    octaves: u32,
+    lacunarity: f32,
+    gain: f32,
+) -> f32 {
+    sum_octaves(u, v, t, octaves, lacunarity, gain, f32::abs)
+}
+
+/// Ridged noise: [`interference_fbm`] over `1 - |octave|`, so sharp ridges
+/// (reaching `1`) form where the field crosses zero. The result lies in
+/// `[0, 1]`.
+pub fn interference_ridged(
+    u: f32,
+    v: f32,
+    t: f32,
+    octaves: u32,
+    lacunarity: f32,
+    gain: f32,
+) -> f32 {
+    sum_octaves(u, v, t, octaves, lacunarity, gain, |x| 1.0 - x.abs())
+}
+
+/// Shared octave loop: `shape` maps each octave's raw field value before it
+/// is weighted. Octaves are offset from one another so their zero crossings
+/// do not line up.
+fn sum_octaves(
+    u: f32,
+    v: f32,
+    t: f32,
+    octaves: u32,
+    lacunarity: f32,
+    gain: f32,
+    shape: impl Fn(f32) -> f32,
+) -> f32 {
+    let mut sum = 0.0_f32;
+    let mut total = 0.0_f32;
+    let mut frequency = 1.0_f32;
+    let mut amplitude = 1.0_f32;
+    for octave in 0..octaves {
+        let offset = octave as f32 * 1.618;
+        let value = interference_field(u * frequency + offset, v * frequency - offset, t);
+        sum += shape(value) * amplitude;
+        total += amplitude.abs();
+        frequency *= lacunarity;
+        amplitude *= gain;
+    }
+    if total > 0.0 {
+        sum / total
+    } else {
+        0.0
+    }
 }
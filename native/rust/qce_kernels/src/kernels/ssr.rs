@@ -1,7 +1,836 @@
-/// Computes helper values for screen-space reflections.
+#[cfg(feature = "alloc")]
+use alloc::{vec, vec::Vec};
+
+use core::f32::consts::TAU;
+
+use crate::error::{check_finite, check_len, KernelError};
+use crate::kernels::taa::sample_bilinear;
+#[cfg(all(not(feature = "std"), not(test)))]
+use crate::math::F32Ext;
+#[cfg(feature = "alloc")]
+use crate::scratch::KernelScratch;
+use crate::utils;
+
+/// Step budget [`ssr_step`] normalizes `step_count` by.
+pub const SSR_DEFAULT_MAX_STEPS: u32 = 32;
+
+/// Computes helper values for screen-space reflections. Non-finite inputs
+/// yield `0.0` rather than propagating NaN.
 pub fn ssr_step(hit_depth: f32, roughness: f32, step_count: u32) -> (f32, f32) {
+    ssr_step_with_budget(hit_depth, roughness, step_count, SSR_DEFAULT_MAX_STEPS)
+}
+
+/// [`ssr_step`] for a renderer whose march budget is `max_steps` rather than
+/// [`SSR_DEFAULT_MAX_STEPS`]: the rough boost saturates once `step_count`
+/// reaches `max_steps`. A budget of `0` is treated as `1`.
+pub fn ssr_step_with_budget(
+    hit_depth: f32,
+    roughness: f32,
+    step_count: u32,
+    max_steps: u32,
+) -> (f32, f32) {
     let edge_fade = (1.0 - hit_depth).clamp(0.0, 1.0).powf(2.0);
     let roughness = (1.0 - roughness).clamp(0.0, 1.0);
-    let rough_boost = (step_count as f32 / 32.0).min(1.0) * roughness;
-    (edge_fade, rough_boost)
+    let rough_boost = (step_count as f32 / max_steps.max(1) as f32).min(1.0) * roughness;
+    (
+        utils::clamp_nan(edge_fade, 0.0),
+        utils::clamp_nan(rough_boost, 0.0),
+    )
+}
+
+/// [`ssr_step`] over whole arrays: element `i` of `edge_out`/`boost_out`
+/// receives the fade and boost for `hit_depth[i]`, `roughness[i]` and
+/// `step_count[i]`. All slices must have the same length.
+pub fn ssr_step_batch(
+    hit_depth: &[f32],
+    roughness: &[f32],
+    step_count: &[u32],
+    edge_out: &mut [f32],
+    boost_out: &mut [f32],
+) {
+    if let Err(err) = ssr_step_batch_checked(hit_depth, roughness, step_count, edge_out, boost_out)
+    {
+        panic!("{}", err);
+    }
+}
+
+/// Fallible [`ssr_step_batch`]: returns an error instead of panicking when
+/// the slice lengths differ.
+pub fn ssr_step_batch_checked(
+    hit_depth: &[f32],
+    roughness: &[f32],
+    step_count: &[u32],
+    edge_out: &mut [f32],
+    boost_out: &mut [f32],
+) -> Result<(), KernelError> {
+    let len = hit_depth.len();
+    check_len("roughness", roughness.len(), len)?;
+    check_len("step count", step_count.len(), len)?;
+    check_len("edge output", edge_out.len(), len)?;
+    check_len("boost output", boost_out.len(), len)?;
+    step_batch(hit_depth, roughness, step_count, edge_out, boost_out);
+    utils::sanitize_slice(edge_out, 0.0);
+    utils::sanitize_slice(boost_out, 0.0);
+    Ok(())
+}
+
+#[cfg(not(feature = "simd"))]
+fn step_batch(
+    hit_depth: &[f32],
+    roughness: &[f32],
+    step_count: &[u32],
+    edge_out: &mut [f32],
+    boost_out: &mut [f32],
+) {
+    for i in 0..hit_depth.len() {
+        (edge_out[i], boost_out[i]) = ssr_step(hit_depth[i], roughness[i], step_count[i]);
+    }
+}
+
+/// Eight lanes at a time with a scalar tail.
+#[cfg(feature = "simd")]
+fn step_batch(
+    hit_depth: &[f32],
+    roughness: &[f32],
+    step_count: &[u32],
+    edge_out: &mut [f32],
+    boost_out: &mut [f32],
+) {
+    use wide::f32x8;
+
+    let zero = f32x8::splat(0.0);
+    let one = f32x8::splat(1.0);
+    let lanes = hit_depth.len() / 8 * 8;
+    for i in (0..lanes).step_by(8) {
+        let depth = f32x8::from(<[f32; 8]>::try_from(&hit_depth[i..i + 8]).unwrap());
+        let rough = f32x8::from(<[f32; 8]>::try_from(&roughness[i..i + 8]).unwrap());
+        let steps = f32x8::from(core::array::from_fn::<f32, 8, _>(|lane| {
+            step_count[i + lane] as f32
+        }));
+
+        let fade = (one - depth).max(zero).min(one);
+        let boost = (steps / f32x8::splat(SSR_DEFAULT_MAX_STEPS as f32)).min(one)
+            * (one - rough).max(zero).min(one);
+        edge_out[i..i + 8].copy_from_slice(&(fade * fade).to_array());
+        boost_out[i..i + 8].copy_from_slice(&boost.to_array());
+    }
+    for i in lanes..hit_depth.len() {
+        (edge_out[i], boost_out[i]) = ssr_step(hit_depth[i], roughness[i], step_count[i]);
+    }
+}
+
+/// [`ssr_step`] plus a Schlick Fresnel factor `f0 + (1 - f0) * (1 - cos_theta)^5`
+/// for the angle between the view ray and the surface normal, so reflections
+/// strengthen towards grazing angles.
+pub fn ssr_step_fresnel(
+    hit_depth: f32,
+    roughness: f32,
+    step_count: u32,
+    cos_theta: f32,
+    f0: f32,
+) -> (f32, f32, f32) {
+    let (edge_fade, rough_boost) = ssr_step(hit_depth, roughness, step_count);
+    let fresnel = f0 + (1.0 - f0) * (1.0 - cos_theta.clamp(0.0, 1.0)).powi(5);
+    (edge_fade, rough_boost, fresnel)
+}
+
+/// Footprint radius of a glossy reflection cone at `hit_distance`, in the same
+/// units as the distance. The cone's half-angle tangent is taken as the GGX
+/// `alpha = roughness^2`, so the radius grows with both roughness and
+/// distance; `log2` of the radius in pixels gives the colour mip to sample.
+pub fn ssr_cone(roughness: f32, hit_distance: f32) -> f32 {
+    let alpha = roughness.clamp(0.0, 1.0).powi(2);
+    hit_distance.max(0.0) * alpha
+}
+
+/// Contact hardening for glossy reflections: `1.0` for a hit at distance `0`,
+/// falling linearly to `0.0` at `max_distance` and beyond. Scale the
+/// [`ssr_cone`] radius by `1.0 - sharpness` so reflections of nearby objects
+/// stay crisp whatever the roughness.
+pub fn ssr_contact_sharpness(hit_distance: f32, max_distance: f32) -> f32 {
+    let distance = hit_distance.max(0.0);
+    if max_distance > 0.0 {
+        utils::clamp_nan(1.0 - distance / max_distance, 0.0).clamp(0.0, 1.0)
+    } else if distance > 0.0 {
+        0.0
+    } else {
+        1.0
+    }
+}
+
+/// Importance-samples a GGX microfacet normal around `normal` from the two
+/// uniform randoms `xi` in `[0, 1)` and returns the unit direction of `view`
+/// reflected about it, ready to trace with [`ssr_march`] for glossy
+/// reflections. `view` points from the surface towards the eye; neither
+/// vector needs to be normalized. GGX `alpha` is `roughness^2`, so roughness
+/// `0` always returns the mirror reflection.
+pub fn ggx_sample_direction(
+    normal: (f32, f32, f32),
+    view: (f32, f32, f32),
+    roughness: f32,
+    xi: (f32, f32),
+) -> (f32, f32, f32) {
+    let n = normalize3(normal);
+    let v = normalize3(view);
+    let alpha = roughness.clamp(0.0, 1.0).powi(2);
+
+    let h = if alpha > 0.0 {
+        let (u1, u2) = (xi.0.clamp(0.0, 1.0), xi.1);
+        let cos_theta = ((1.0 - u1) / (1.0 + (alpha * alpha - 1.0) * u1)).sqrt();
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let (sin_phi, cos_phi) = (TAU * u2).sin_cos();
+        let (t, b) = tangent_frame(n);
+        let (x, y) = (sin_theta * cos_phi, sin_theta * sin_phi);
+        normalize3((
+            t.0 * x + b.0 * y + n.0 * cos_theta,
+            t.1 * x + b.1 * y + n.1 * cos_theta,
+            t.2 * x + b.2 * y + n.2 * cos_theta,
+        ))
+    } else {
+        n
+    };
+
+    let v_dot_h = v.0 * h.0 + v.1 * h.1 + v.2 * h.2;
+    (
+        2.0 * v_dot_h * h.0 - v.0,
+        2.0 * v_dot_h * h.1 - v.1,
+        2.0 * v_dot_h * h.2 - v.2,
+    )
+}
+
+/// Scales `v` to unit length; the zero vector is returned unchanged.
+fn normalize3(v: (f32, f32, f32)) -> (f32, f32, f32) {
+    let len = (v.0 * v.0 + v.1 * v.1 + v.2 * v.2).sqrt();
+    if len > 0.0 {
+        (v.0 / len, v.1 / len, v.2 / len)
+    } else {
+        v
+    }
+}
+
+/// Tangent and bitangent completing an orthonormal basis with the unit
+/// vector `n` (Duff et al. 2017).
+fn tangent_frame(n: (f32, f32, f32)) -> ((f32, f32, f32), (f32, f32, f32)) {
+    let sign = if n.2 >= 0.0 { 1.0 } else { -1.0 };
+    let a = -1.0 / (sign + n.2);
+    let b = n.0 * n.1 * a;
+    (
+        (1.0 + sign * n.0 * n.0 * a, sign * b, -sign * n.0),
+        (b, sign + n.1 * n.1 * a, -n.1),
+    )
+}
+
+/// Fades reflections towards every screen border: `1.0` more than
+/// `fade_width` (in UV units) from all four edges, falling linearly to `0.0`
+/// at the edge. Multiply with the `edge_fade` from [`ssr_step`].
+pub fn ssr_edge_fade(hit_uv: (f32, f32), fade_width: f32) -> f32 {
+    let (u, v) = hit_uv;
+    let border = u.min(1.0 - u).min(v).min(1.0 - v);
+    if fade_width > 0.0 {
+        (border / fade_width).clamp(0.0, 1.0)
+    } else if border > 0.0 {
+        1.0
+    } else {
+        0.0
+    }
+}
+
+/// Stabilises a noisy SSR reflection buffer over time the way TAA does for
+/// colour: each pixel's history is fetched from `prev_ssr` at the position
+/// its `motion` vector (in pixels, as for TAA) points back to and blended in
+/// with weight `blend`. Pass an empty `motion` slice when no motion is
+/// available (a static camera); history is then fetched in place.
+///
+/// Both buffers are RGBA, with the reflection colour in RGB and the hit mask
+/// (or fade weight) in alpha. History is rejected, leaving the current value,
+/// where the mask flipped across `0.5` between the frames or the fetch falls
+/// off screen, so reflections that appear or vanish do not ghost.
+pub fn ssr_temporal_accumulate(
+    curr_ssr: &[f32],
+    prev_ssr: &[f32],
+    motion: &[f32],
+    w: usize,
+    h: usize,
+    blend: f32,
+    out: &mut [f32],
+) {
+    if let Err(err) = ssr_temporal_accumulate_checked(curr_ssr, prev_ssr, motion, w, h, blend, out)
+    {
+        panic!("{}", err);
+    }
+}
+
+/// Fallible [`ssr_temporal_accumulate`]: returns an error instead of
+/// panicking on mismatched buffer lengths or a non-finite `blend`.
+pub fn ssr_temporal_accumulate_checked(
+    curr_ssr: &[f32],
+    prev_ssr: &[f32],
+    motion: &[f32],
+    w: usize,
+    h: usize,
+    blend: f32,
+    out: &mut [f32],
+) -> Result<(), KernelError> {
+    let pixel_count = w.checked_mul(h).ok_or(KernelError::DimensionOverflow)?;
+    let len = pixel_count
+        .checked_mul(4)
+        .ok_or(KernelError::DimensionOverflow)?;
+    check_len("current SSR", curr_ssr.len(), len)?;
+    check_len("previous SSR", prev_ssr.len(), len)?;
+    if !motion.is_empty() {
+        let motion_len = pixel_count
+            .checked_mul(2)
+            .ok_or(KernelError::DimensionOverflow)?;
+        check_len("motion", motion.len(), motion_len)?;
+    }
+    check_len("output", out.len(), len)?;
+    check_finite("blend", blend)?;
+
+    let blend = blend.clamp(0.0, 1.0);
+    for (idx, (curr, out)) in curr_ssr
+        .chunks_exact(4)
+        .zip(out.chunks_exact_mut(4))
+        .enumerate()
+    {
+        let (x, y) = ((idx % w) as f32, (idx / w) as f32);
+        let (mx, my) = if motion.is_empty() {
+            (0.0, 0.0)
+        } else {
+            (motion[idx * 2], motion[idx * 2 + 1])
+        };
+        let (hx, hy) = (x - mx, y - my);
+        let on_screen = hx >= -0.5 && hx <= w as f32 - 0.5 && hy >= -0.5 && hy <= h as f32 - 0.5;
+        let history = sample_bilinear(prev_ssr, w * 4, w, h, 4, hx, hy, false);
+        let mask_changed = (curr[3] >= 0.5) != (history[3] >= 0.5);
+        if !on_screen || mask_changed {
+            out.copy_from_slice(curr);
+            continue;
+        }
+        for (c, value) in out.iter_mut().enumerate() {
+            *value = curr[c] + (history[c] - curr[c]) * blend;
+        }
+    }
+    Ok(())
+}
+
+/// Tunable parameters for [`ssr_march_with`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SsrMarchParams {
+    /// Maximum number of steps before the ray gives up.
+    pub max_steps: u32,
+    /// Factor by which each step is longer than the previous one, starting at
+    /// one pixel, so distant parts of the ray cover more screen for the same
+    /// budget. Combine with `refine_steps` to recover precision far away.
+    /// `1.0` (or less) keeps fixed one-pixel steps.
+    pub step_growth: f32,
+    /// Assumed depth extent of every surface. A ray more than this far behind
+    /// the depth buffer has passed behind the object rather than into it, so
+    /// it is treated as free space and keeps marching.
+    pub thickness: f32,
+    /// Binary-search iterations run between the last two steps once a
+    /// crossing is found, to pin down the intersection. `0` returns the
+    /// coarse hit.
+    pub refine_steps: u32,
+    /// Fraction of a step by which the ray start is pushed forward using
+    /// interleaved gradient noise over the origin pixel, trading banding for
+    /// noise that TAA resolves. `0.0` starts every ray on its surface.
+    pub jitter_amount: f32,
+    /// Frame index fed to the start-offset noise so the pattern changes every
+    /// frame.
+    pub jitter_seed: u32,
+    /// Scene depth at or beyond which a sample is sky/background: the ray
+    /// stops there and reports a miss. `f32::INFINITY` disables the early-out.
+    pub sky_depth: f32,
+    /// Roughness of the reflecting surface, which widens the reflection cone
+    /// reported as [`SsrMarchResult::cone_radius`].
+    pub roughness: f32,
+}
+
+impl Default for SsrMarchParams {
+    fn default() -> Self {
+        SsrMarchParams {
+            max_steps: 64,
+            step_growth: 1.0,
+            thickness: 0.1,
+            refine_steps: 0,
+            jitter_amount: 0.0,
+            jitter_seed: 0,
+            sky_depth: f32::INFINITY,
+            roughness: 0.0,
+        }
+    }
+}
+
+/// Outcome of a reflection march.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SsrMarchResult {
+    /// UV of the reflected surface, or `None` on a miss.
+    pub hit: Option<(f32, f32)>,
+    /// Steps consumed before the ray hit, left the screen or ran out.
+    pub steps_taken: u32,
+    /// Footprint radius, in pixels, of the glossy reflection cone at the hit:
+    /// [`ssr_cone`] of `params.roughness` over the screen-space distance
+    /// marched from the origin. `log2` of it picks the colour mip to blur
+    /// with; `0.0` on a miss.
+    pub cone_radius: f32,
+}
+
+impl SsrMarchResult {
+    fn miss(steps_taken: u32) -> Self {
+        SsrMarchResult {
+            hit: None,
+            steps_taken,
+            cone_radius: 0.0,
+        }
+    }
+
+    /// A hit at `hit_uv` for a ray that left `origin_uv` on a `w * h` screen.
+    fn hit(
+        hit_uv: (f32, f32),
+        origin_uv: (f32, f32),
+        w: usize,
+        h: usize,
+        steps_taken: u32,
+        roughness: f32,
+    ) -> Self {
+        let dx = (hit_uv.0 - origin_uv.0) * w as f32;
+        let dy = (hit_uv.1 - origin_uv.1) * h as f32;
+        SsrMarchResult {
+            hit: Some(hit_uv),
+            steps_taken,
+            cone_radius: ssr_cone(roughness, (dx * dx + dy * dy).sqrt()),
+        }
+    }
+}
+
+/// Marches a reflection ray through a linear depth buffer and returns the UV
+/// where it first enters a surface, i.e. lands behind the depth buffer by no
+/// more than `thickness` after being outside that slab on the previous step.
+///
+/// `depth` holds one linear depth per pixel (larger = farther). The ray starts
+/// on the surface at `origin_uv` and travels along `reflect_dir = (du, dv,
+/// ddepth)`, advancing one pixel per step (see `step_growth`) for at most
+/// `max_steps` steps.
+/// `normal` optionally holds a normal triple per pixel; surfaces facing away
+/// from the ray cannot be hit. The normals must be expressed in the same
+/// `(u, v, depth)` space as `reflect_dir`, not in view space: for the surface
+/// stored in `depth` that is `(∂depth/∂u, ∂depth/∂v, -1)` (pointing towards
+/// the camera), e.g. from the depth buffer's screen-space derivatives. Pass
+/// an empty slice to skip the test. The hit is `None` when the ray leaves the
+/// screen or runs out of steps.
+#[allow(clippy::too_many_arguments)]
+pub fn ssr_march(
+    depth: &[f32],
+    normal: &[f32],
+    w: usize,
+    h: usize,
+    origin_uv: (f32, f32),
+    reflect_dir: (f32, f32, f32),
+    max_steps: u32,
+    thickness: f32,
+) -> SsrMarchResult {
+    let params = SsrMarchParams {
+        max_steps,
+        thickness,
+        ..SsrMarchParams::default()
+    };
+    ssr_march_with(depth, normal, w, h, origin_uv, reflect_dir, &params)
+}
+
+/// [`ssr_march`] with the full set of [`SsrMarchParams`].
+pub fn ssr_march_with(
+    depth: &[f32],
+    normal: &[f32],
+    w: usize,
+    h: usize,
+    origin_uv: (f32, f32),
+    reflect_dir: (f32, f32, f32),
+    params: &SsrMarchParams,
+) -> SsrMarchResult {
+    let Some((step, mut ray)) = ray_start(depth, normal, w, h, origin_uv, reflect_dir, params)
+    else {
+        return SsrMarchResult::miss(0);
+    };
+    // The ray starts inside its own surface's slab; it has to leave it before
+    // a hit counts.
+    let mut prev_inside = true;
+    let growth = params.step_growth.max(1.0);
+    let mut step_len = 1.0_f32;
+    for steps_taken in 1..=params.max_steps {
+        let prev = ray;
+        ray = (
+            ray.0 + step.0 * step_len,
+            ray.1 + step.1 * step_len,
+            ray.2 + step.2 * step_len,
+        );
+        step_len *= growth;
+        if !(0.0..=1.0).contains(&ray.0) || !(0.0..=1.0).contains(&ray.1) {
+            return SsrMarchResult::miss(steps_taken);
+        }
+
+        let idx = texel(w, h, ray.0, ray.1);
+        if depth[idx] >= params.sky_depth {
+            return SsrMarchResult::miss(steps_taken);
+        }
+        let inside = in_slab(ray.2 - depth[idx], params.thickness);
+        if !prev_inside && inside && faces_ray(normal, idx, reflect_dir) {
+            let hit = refine(
+                depth,
+                w,
+                h,
+                prev,
+                ray,
+                params.thickness,
+                params.refine_steps,
+            );
+            return SsrMarchResult::hit(
+                (hit.0, hit.1),
+                origin_uv,
+                w,
+                h,
+                steps_taken,
+                params.roughness,
+            );
+        }
+        prev_inside = inside;
+    }
+    SsrMarchResult::miss(params.max_steps)
+}
+
+/// A position along a reflection ray: `(u, v, linear depth)`.
+type RayPoint = (f32, f32, f32);
+
+/// Validates the march inputs and returns the per-step ray delta (one pixel
+/// along the major axis) and the possibly dithered start point, or `None` when
+/// there is nothing to march.
+fn ray_start(
+    depth: &[f32],
+    normal: &[f32],
+    w: usize,
+    h: usize,
+    origin_uv: (f32, f32),
+    reflect_dir: (f32, f32, f32),
+    params: &SsrMarchParams,
+) -> Option<(RayPoint, RayPoint)> {
+    let pixel_count = w
+        .checked_mul(h)
+        .expect("image dimensions overflow when computing pixel count");
+    assert!(
+        depth.len() == pixel_count,
+        "depth buffer length {} does not match expected {}",
+        depth.len(),
+        pixel_count
+    );
+    assert!(
+        normal.is_empty() || normal.len() == pixel_count * 3,
+        "normal buffer length {} does not match expected {}",
+        normal.len(),
+        pixel_count * 3
+    );
+    if pixel_count == 0 {
+        return None;
+    }
+
+    let (du, dv, dz) = reflect_dir;
+    // Scale the direction so each step crosses one pixel along its major axis.
+    let pixels = (du * w as f32).abs().max((dv * h as f32).abs());
+    if !pixels.is_finite() || pixels <= 0.0 {
+        return None;
+    }
+    let step = (du / pixels, dv / pixels, dz / pixels);
+
+    let (u, v) = origin_uv;
+    let mut ray = (u, v, depth[texel(w, h, u, v)]);
+    if params.jitter_amount != 0.0 {
+        let origin = texel(w, h, u, v);
+        let noise = utils::interleaved_gradient_noise(
+            (origin % w) as u32,
+            (origin / w) as u32,
+            params.jitter_seed,
+        );
+        let offset = noise * params.jitter_amount;
+        ray = (
+            ray.0 + step.0 * offset,
+            ray.1 + step.1 * offset,
+            ray.2 + step.2 * offset,
+        );
+    }
+    Some((step, ray))
+}
+
+/// Min/max depth mip chain used by [`ssr_march_hiz`]. Level 0 is the depth
+/// buffer itself; each further level halves both dimensions (rounding up) and
+/// stores the min and max of the 2x2 texels below it.
+#[cfg(feature = "alloc")]
+#[derive(Clone, Debug)]
+pub struct HiZPyramid {
+    levels: Vec<HiZLevel>,
+}
+
+#[cfg(feature = "alloc")]
+#[derive(Clone, Debug)]
+struct HiZLevel {
+    width: usize,
+    height: usize,
+    min: Vec<f32>,
+    max: Vec<f32>,
+}
+
+#[cfg(feature = "alloc")]
+impl HiZPyramid {
+    /// Returns the mip buffers to `scratch` for the next
+    /// [`build_hiz_with_scratch`].
+    pub fn recycle(self, scratch: &mut KernelScratch) {
+        for level in self.levels {
+            scratch.give(level.min);
+            scratch.give(level.max);
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.levels[0].width
+    }
+
+    pub fn height(&self) -> usize {
+        self.levels[0].height
+    }
+
+    /// Number of mip levels, including the full-resolution one.
+    pub fn level_count(&self) -> usize {
+        self.levels.len()
+    }
+
+    fn depth(&self) -> &[f32] {
+        &self.levels[0].min
+    }
+
+    /// Depth range over the inclusive cell rectangle `(x0, y0)..=(x1, y1)` of
+    /// `level`.
+    fn range(&self, level: usize, x0: usize, y0: usize, x1: usize, y1: usize) -> (f32, f32) {
+        let level = &self.levels[level];
+        let mut lo = f32::INFINITY;
+        let mut hi = f32::NEG_INFINITY;
+        for y in y0..=y1 {
+            for x in x0..=x1 {
+                lo = lo.min(level.min[y * level.width + x]);
+                hi = hi.max(level.max[y * level.width + x]);
+            }
+        }
+        (lo, hi)
+    }
+}
+
+/// Builds the [`HiZPyramid`] for a `w * h` linear depth buffer.
+#[cfg(feature = "alloc")]
+pub fn build_hiz(depth: &[f32], w: usize, h: usize) -> HiZPyramid {
+    build_hiz_with_scratch(depth, w, h, &mut KernelScratch::new())
+}
+
+/// [`build_hiz`] taking the mip buffers from `scratch`. Hand them back with
+/// [`HiZPyramid::recycle`] once the pyramid is no longer needed, so the next
+/// frame's pyramid reuses them.
+#[cfg(feature = "alloc")]
+pub fn build_hiz_with_scratch(
+    depth: &[f32],
+    w: usize,
+    h: usize,
+    scratch: &mut KernelScratch,
+) -> HiZPyramid {
+    let pixel_count = w
+        .checked_mul(h)
+        .expect("image dimensions overflow when computing pixel count");
+    assert!(
+        depth.len() == pixel_count,
+        "depth buffer length {} does not match expected {}",
+        depth.len(),
+        pixel_count
+    );
+
+    let mut levels = vec![HiZLevel {
+        width: w,
+        height: h,
+        min: scratch.take_copy(depth),
+        max: scratch.take_copy(depth),
+    }];
+    while let Some(below) = levels.last().filter(|l| l.width > 1 || l.height > 1) {
+        let (width, height) = (below.width.div_ceil(2), below.height.div_ceil(2));
+        let mut min = scratch.take(width * height, f32::INFINITY);
+        let mut max = scratch.take(width * height, f32::NEG_INFINITY);
+        for y in 0..below.height {
+            for x in 0..below.width {
+                let src = y * below.width + x;
+                let dst = (y / 2) * width + x / 2;
+                min[dst] = min[dst].min(below.min[src]);
+                max[dst] = max[dst].max(below.max[src]);
+            }
+        }
+        levels.push(HiZLevel {
+            width,
+            height,
+            min,
+            max,
+        });
+    }
+    HiZPyramid { levels }
+}
+
+/// [`ssr_march_with`] accelerated by a [`HiZPyramid`]. Stretches of the ray
+/// that the pyramid proves cannot enter any surface are skipped a whole mip
+/// cell at a time, dropping to finer levels only near potential hits, so the
+/// hit matches the linear march while touching far fewer depth texels.
+///
+/// `steps_taken` counts traversal iterations (one pyramid lookup each) and
+/// `max_steps` bounds them. `step_growth` is ignored: the pyramid already
+/// provides the long strides.
+#[cfg(feature = "alloc")]
+pub fn ssr_march_hiz(
+    pyramid: &HiZPyramid,
+    normal: &[f32],
+    origin_uv: (f32, f32),
+    reflect_dir: (f32, f32, f32),
+    params: &SsrMarchParams,
+) -> SsrMarchResult {
+    let (w, h) = (pyramid.width(), pyramid.height());
+    let depth = pyramid.depth();
+    let Some((step, start)) = ray_start(depth, normal, w, h, origin_uv, reflect_dir, params) else {
+        return SsrMarchResult::miss(0);
+    };
+    let at = |k: u64| {
+        let t = k as f32;
+        (
+            start.0 + step.0 * t,
+            start.1 + step.1 * t,
+            start.2 + step.2 * t,
+        )
+    };
+    let on_screen = |p: RayPoint| (0.0..=1.0).contains(&p.0) && (0.0..=1.0).contains(&p.1);
+
+    let top = pyramid.level_count() - 1;
+    let mut level = 0;
+    let mut k = 0_u64;
+    let mut prev_inside = true;
+    for steps_taken in 1..=params.max_steps {
+        if level > 0 {
+            // Steps k+1 ..= k+span move at most one cell per axis at this
+            // level, so they stay within a 2x2 block of cells.
+            let span = 1_u64 << level;
+            let (first, last) = (at(k + 1), at(k + span));
+            if !on_screen(last) {
+                level -= 1;
+                continue;
+            }
+            let (fx, fy) = texel_xy(w, h, first.0, first.1);
+            let (lx, ly) = texel_xy(w, h, last.0, last.1);
+            let (lo, hi) = pyramid.range(
+                level,
+                fx.min(lx) >> level,
+                fy.min(ly) >> level,
+                fx.max(lx) >> level,
+                fy.max(ly) >> level,
+            );
+            let (ray_lo, ray_hi) = (first.2.min(last.2), first.2.max(last.2));
+            let empty = (ray_hi < lo || ray_lo > hi + params.thickness) && hi < params.sky_depth;
+            if empty {
+                k += span;
+                prev_inside = false;
+                level = (level + 1).min(top);
+            } else {
+                level -= 1;
+            }
+            continue;
+        }
+
+        let prev = at(k);
+        k += 1;
+        let ray = at(k);
+        if !on_screen(ray) {
+            return SsrMarchResult::miss(steps_taken);
+        }
+        let idx = texel(w, h, ray.0, ray.1);
+        if depth[idx] >= params.sky_depth {
+            return SsrMarchResult::miss(steps_taken);
+        }
+        let inside = in_slab(ray.2 - depth[idx], params.thickness);
+        if !prev_inside && inside && faces_ray(normal, idx, reflect_dir) {
+            let hit = refine(
+                depth,
+                w,
+                h,
+                prev,
+                ray,
+                params.thickness,
+                params.refine_steps,
+            );
+            return SsrMarchResult::hit(
+                (hit.0, hit.1),
+                origin_uv,
+                w,
+                h,
+                steps_taken,
+                params.roughness,
+            );
+        }
+        prev_inside = inside;
+        level = top.min(1);
+    }
+    SsrMarchResult::miss(params.max_steps)
+}
+
+/// Whether a ray `behind` the depth buffer lies within a surface of the given
+/// thickness.
+#[inline]
+fn in_slab(behind: f32, thickness: f32) -> bool {
+    behind >= 0.0 && behind <= thickness
+}
+
+/// Bisects the segment between `front` (outside any surface) and `back`
+/// (inside one), returning the first point found inside.
+fn refine(
+    depth: &[f32],
+    w: usize,
+    h: usize,
+    mut front: RayPoint,
+    mut back: RayPoint,
+    thickness: f32,
+    iterations: u32,
+) -> RayPoint {
+    for _ in 0..iterations {
+        let mid = (
+            (front.0 + back.0) * 0.5,
+            (front.1 + back.1) * 0.5,
+            (front.2 + back.2) * 0.5,
+        );
+        if in_slab(mid.2 - depth[texel(w, h, mid.0, mid.1)], thickness) {
+            back = mid;
+        } else {
+            front = mid;
+        }
+    }
+    back
+}
+
+/// Index of the pixel containing `(u, v)`, clamped to the image.
+fn texel(w: usize, h: usize, u: f32, v: f32) -> usize {
+    let (x, y) = texel_xy(w, h, u, v);
+    y * w + x
+}
+
+/// Coordinates of the pixel containing `(u, v)`, clamped to the image.
+fn texel_xy(w: usize, h: usize, u: f32, v: f32) -> (usize, usize) {
+    let x = ((u * w as f32).max(0.0) as usize).min(w - 1);
+    let y = ((v * h as f32).max(0.0) as usize).min(h - 1);
+    (x, y)
+}
+
+/// Whether the surface at `idx` faces the incoming ray; always true without
+/// normals. `normal` and `dir` are both in `(u, v, depth)` space (see
+/// [`ssr_march`]), so their dot product is negative exactly when the ray
+/// runs into the front of the surface. Only the sign matters, so neither
+/// needs to be normalized.
+fn faces_ray(normal: &[f32], idx: usize, dir: (f32, f32, f32)) -> bool {
+    if normal.is_empty() {
+        return true;
+    }
+    let n = &normal[idx * 3..idx * 3 + 3];
+    n[0] * dir.0 + n[1] * dir.1 + n[2] * dir.2 < 0.0
 }
@@ -1,7 +1,261 @@
-/// Simple temporal anti-aliasing history blend. The current implementation
-/// performs a straight lerp between the current and previous RGB buffers.
-/// Motion vectors are accepted to keep the signature stable for future
-/// reprojection improvements.
+#[cfg(feature = "alloc")]
+use alloc::{vec, vec::Vec};
+
+use crate::error::{check_finite, check_len, KernelError};
+use crate::kernel::Kernel;
+#[cfg(all(not(feature = "std"), not(test)))]
+use crate::math::F32Ext;
+#[cfg(feature = "alloc")]
+use crate::scratch::KernelScratch;
+use crate::utils;
+
+/// Filter used to fetch the previous frame at the reprojected position.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HistorySampling {
+    /// Four-tap bilinear fetch. Cheap, but softens the history every frame.
+    #[default]
+    Bilinear,
+    /// Sixteen-tap bicubic Catmull-Rom fetch, which preserves sharpness.
+    CatmullRom,
+}
+
+/// Tunable parameters for [`taa_reproject_with`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TaaParams {
+    /// Weight given to the reprojected history sample, clamped to `[0, 1]`.
+    pub blend: f32,
+    /// Motion-vector magnitude (in pixels) above which the history sample is
+    /// treated as disoccluded and discarded. `f32::INFINITY` disables the test.
+    pub disocclusion_threshold: f32,
+    /// How far (per channel) the reprojected history may fall outside the
+    /// min/max range of the current 3x3 neighbourhood before it is discarded.
+    /// `f32::INFINITY` disables the test.
+    pub rejection_threshold: f32,
+    /// Falloff `k` of the velocity weighting `blend * exp(-k * |motion|)`, so
+    /// fast-moving pixels trust history less than static ones. `0.0` keeps the
+    /// blend constant.
+    pub velocity_weight: f32,
+    /// Replace non-finite input samples with `0.0` before blending so a single
+    /// NaN/Inf cannot poison the history, and guarantee a finite output.
+    pub sanitize: bool,
+    /// Maximum difference between the current depth and the previous depth at
+    /// the reprojected location before history is treated as occluded. Only
+    /// used when the frame carries depth buffers.
+    pub depth_tolerance: f32,
+    /// Filter used to fetch history at the reprojected position.
+    pub sampling: HistorySampling,
+    /// Blend in Reinhard-tonemapped space (`x / (1 + x)`) and invert
+    /// afterwards, so very bright samples do not dominate the history.
+    pub hdr: bool,
+    /// Treat the colour channels of `curr` and `prev` as sRGB-encoded: decode
+    /// them to linear before blending and re-encode the result, so the
+    /// history average does not darken. Filtering and rejection still see the
+    /// encoded values; alpha is never converted.
+    pub decode_srgb: bool,
+    /// Longest motion vector (in pixels) used for the history fetch. Longer
+    /// vectors keep their direction but are shortened to this length so broken
+    /// motion estimation cannot pull history from across the screen. Must not
+    /// be negative or NaN; `f32::INFINITY` disables the cap.
+    pub max_motion: f32,
+    /// Replace each pixel's motion vector with that of the closest-depth pixel
+    /// in its 3x3 neighbourhood, so thin moving edges reproject with the
+    /// object's motion rather than the background's. Requires depth buffers;
+    /// smaller depth values are treated as closer.
+    pub dilate_motion: bool,
+    /// Strength of the 5-tap sharpen applied to the resolved output,
+    /// `out += sharpness * (centre - mean(neighbours))`, clamped to the range
+    /// of the taps so edges do not ring. `0.0` disables it.
+    pub sharpness: f32,
+    /// Largest relative luminance change allowed between the reprojected
+    /// history and the current sample per frame; the current colour is
+    /// rescaled to stay within `hist_luma * (1 ± r)`, damping flicker on
+    /// sub-pixel features. `f32::INFINITY` disables the limit.
+    pub flicker_rate: f32,
+    /// Per-channel history weights that replace `blend` when set, e.g. so
+    /// alpha or bloom can settle at a different rate than colour. Entries past
+    /// the frame's channel count are ignored.
+    pub channel_blend: Option<[f32; 4]>,
+}
+
+impl Default for TaaParams {
+    fn default() -> Self {
+        TaaParams {
+            blend: 0.9,
+            disocclusion_threshold: f32::INFINITY,
+            rejection_threshold: f32::INFINITY,
+            velocity_weight: 0.0,
+            sanitize: false,
+            depth_tolerance: 0.01,
+            sampling: HistorySampling::Bilinear,
+            hdr: false,
+            decode_srgb: false,
+            max_motion: f32::INFINITY,
+            dilate_motion: false,
+            sharpness: 0.0,
+            flicker_rate: f32::INFINITY,
+            channel_blend: None,
+        }
+    }
+}
+
+/// Input buffers for a single TAA resolve.
+///
+/// `curr` and `prev` hold interleaved pixels of `channels` floats (RGB by
+/// default, or RGBA), with consecutive rows `curr_stride` and `prev_stride`
+/// floats apart (tightly packed by default); the output rows are
+/// `out_stride` floats apart and any row padding is left untouched. `motion` holds one
+/// `(dx, dy)` pair per pixel, in pixels, pointing from the previous frame to
+/// the current one; pass an empty slice when no motion is available.
+/// `curr_depth`/`prev_depth` are optional per-pixel depth buffers (both empty
+/// or both `width * height` long) used to reject occluded history.
+///
+/// `jitter` is the sub-pixel projection offset, in pixels, the current frame
+/// was rendered with, so pixel `x` shows the scene at `x + jitter`. The usual
+/// sequence is Halton(2, 3) remapped to `[-0.5, 0.5]` and cycled every 8-16
+/// frames; the current frame is resampled at `x - jitter` so history
+/// accumulates an unjittered image.
+///
+/// With `planar` set (see [`with_planar_layout`](Self::with_planar_layout))
+/// `curr`, `prev` and the output instead hold one tightly packed
+/// `width * height` plane per channel, one after another.
+#[derive(Clone, Copy, Debug)]
+pub struct TaaFrame<'a> {
+    pub curr: &'a [f32],
+    pub prev: &'a [f32],
+    pub motion: &'a [f32],
+    pub curr_depth: &'a [f32],
+    pub prev_depth: &'a [f32],
+    pub width: usize,
+    pub height: usize,
+    pub channels: usize,
+    pub curr_stride: usize,
+    pub prev_stride: usize,
+    pub out_stride: usize,
+    pub jitter: (f32, f32),
+    pub planar: bool,
+}
+
+impl<'a> TaaFrame<'a> {
+    pub fn new(
+        curr: &'a [f32],
+        prev: &'a [f32],
+        motion: &'a [f32],
+        width: usize,
+        height: usize,
+    ) -> Self {
+        // Saturating so oversized dimensions are reported by validation
+        // rather than overflowing here.
+        let row_len = width.saturating_mul(3);
+        TaaFrame {
+            curr,
+            prev,
+            motion,
+            curr_depth: &[],
+            prev_depth: &[],
+            width,
+            height,
+            channels: 3,
+            curr_stride: row_len,
+            prev_stride: row_len,
+            out_stride: row_len,
+            jitter: (0.0, 0.0),
+            planar: false,
+        }
+    }
+
+    /// Sets the distance, in floats, between the starts of consecutive rows of
+    /// `curr`, `prev` and the output, for frames living in padded textures.
+    pub fn with_row_strides(mut self, curr: usize, prev: usize, out: usize) -> Self {
+        self.curr_stride = curr;
+        self.prev_stride = prev;
+        self.out_stride = out;
+        self
+    }
+
+    /// Sets the number of interleaved channels per pixel: 3 for RGB or 4 for
+    /// RGBA. Colour-only processing (HDR tonemapping, the luminance limit)
+    /// leaves the fourth channel alone. Resets the row strides to tightly
+    /// packed, so call it before [`with_row_strides`](Self::with_row_strides).
+    pub fn with_channels(mut self, channels: usize) -> Self {
+        let row_len = self.width.saturating_mul(channels);
+        self.channels = channels;
+        self.curr_stride = row_len;
+        self.prev_stride = row_len;
+        self.out_stride = row_len;
+        self
+    }
+
+    /// Switches `curr`, `prev` and the output to planar storage: all of
+    /// channel 0, then all of channel 1, and so on, as decoded video often
+    /// arrives. Planar frames have no row padding, so this resets the row
+    /// strides to tightly packed. Planar resolves need the `alloc` feature.
+    pub fn with_planar_layout(self) -> Self {
+        let channels = self.channels;
+        TaaFrame {
+            planar: true,
+            ..self.with_channels(channels)
+        }
+    }
+
+    /// Where the texels of a buffer with rows `stride` floats apart live.
+    fn texels(&self, stride: usize) -> Texels {
+        if self.planar {
+            Texels {
+                row: self.width,
+                pixel: 1,
+                channel: self.width * self.height,
+            }
+        } else {
+            Texels::interleaved(stride, self.channels)
+        }
+    }
+
+    fn is_tightly_packed(&self) -> bool {
+        let row_len = self.width * self.channels;
+        self.curr_stride == row_len && self.prev_stride == row_len && self.out_stride == row_len
+    }
+
+    /// Sets the sub-pixel jitter the current frame was rendered with.
+    pub fn with_jitter(mut self, jitter_x: f32, jitter_y: f32) -> Self {
+        self.jitter = (jitter_x, jitter_y);
+        self
+    }
+
+    /// Attaches depth buffers for the current and previous frame.
+    pub fn with_depth(mut self, curr_depth: &'a [f32], prev_depth: &'a [f32]) -> Self {
+        self.curr_depth = curr_depth;
+        self.prev_depth = prev_depth;
+        self
+    }
+
+    #[inline]
+    fn motion_at(&self, idx: usize) -> (f32, f32) {
+        if self.motion.is_empty() {
+            (0.0, 0.0)
+        } else {
+            (self.motion[idx * 2], self.motion[idx * 2 + 1])
+        }
+    }
+
+    /// Motion of the closest-depth pixel in the 3x3 neighbourhood of `(x, y)`.
+    fn dilated_motion_at(&self, x: usize, y: usize) -> (f32, f32) {
+        let (w, h) = (self.width, self.height);
+        let mut closest = y * w + x;
+        for ny in y.saturating_sub(1)..=(y + 1).min(h - 1) {
+            for nx in x.saturating_sub(1)..=(x + 1).min(w - 1) {
+                let idx = ny * w + nx;
+                if self.curr_depth[idx] < self.curr_depth[closest] {
+                    closest = idx;
+                }
+            }
+        }
+        self.motion_at(closest)
+    }
+}
+
+/// Temporal anti-aliasing history blend. The previous frame is reprojected
+/// along the motion vectors and lerped with the current frame using `blend`
+/// as the history weight.
 pub fn taa_reproject(
     curr: &[f32],
     prev: &[f32],
@@ -11,51 +265,1350 @@ pub fn taa_reproject(
     blend: f32,
     out: &mut [f32],
 ) {
-    let pixel_count = w
+    let frame = TaaFrame::new(curr, prev, motion, w, h);
+    let params = TaaParams {
+        blend,
+        ..TaaParams::default()
+    };
+    taa_reproject_with(&frame, &params, out, &mut []);
+}
+
+/// Fallible [`taa_reproject`]: returns an error instead of panicking when the
+/// buffers do not match the dimensions or `blend` is not finite.
+pub fn taa_reproject_checked(
+    curr: &[f32],
+    prev: &[f32],
+    motion: &[f32],
+    w: usize,
+    h: usize,
+    blend: f32,
+    out: &mut [f32],
+) -> Result<(), KernelError> {
+    let frame = TaaFrame::new(curr, prev, motion, w, h);
+    let params = TaaParams {
+        blend,
+        ..TaaParams::default()
+    };
+    taa_reproject_with_checked(&frame, &params, out, &mut [])
+}
+
+/// [`taa_reproject`] for RGBA frames with a separate history weight per
+/// channel, in `[r, g, b, a]` order.
+pub fn taa_reproject_per_channel(
+    curr: &[f32],
+    prev: &[f32],
+    motion: &[f32],
+    w: usize,
+    h: usize,
+    blend: [f32; 4],
+    out: &mut [f32],
+) {
+    let frame = TaaFrame::new(curr, prev, motion, w, h).with_channels(4);
+    let params = TaaParams {
+        channel_blend: Some(blend),
+        ..TaaParams::default()
+    };
+    taa_reproject_with(&frame, &params, out, &mut []);
+}
+
+/// [`taa_reproject`] for planar RGB frames: `curr`, `prev` and `out` each
+/// hold a red, a green and a blue plane of `w * h` floats, so planar sources
+/// need no interleaving pass. The result matches the interleaved resolve
+/// channel for channel.
+#[cfg(feature = "alloc")]
+pub fn taa_reproject_planar(
+    curr: &[f32],
+    prev: &[f32],
+    motion: &[f32],
+    w: usize,
+    h: usize,
+    blend: f32,
+    out: &mut [f32],
+) {
+    let frame = TaaFrame::new(curr, prev, motion, w, h).with_planar_layout();
+    let params = TaaParams {
+        blend,
+        ..TaaParams::default()
+    };
+    taa_reproject_with(&frame, &params, out, &mut []);
+}
+
+/// Full TAA resolve. History is fetched at `pixel - motion` and rejected
+/// (falling back to the current colour) when it reprojects from off-screen or
+/// trips one of the disocclusion tests in `params`.
+///
+/// `out_confidence` optionally receives one value per pixel recording the
+/// history weight actually used (`0.0` = rejected; the first channel's weight
+/// when `channel_blend` is set); pass an empty slice to skip it.
+pub fn taa_reproject_with(
+    frame: &TaaFrame<'_>,
+    params: &TaaParams,
+    out: &mut [f32],
+    out_confidence: &mut [f32],
+) {
+    if let Err(err) = check_confidence(frame, params, out.len(), out_confidence.len()) {
+        panic!("{}", err);
+    }
+    resolve(frame, params, out, out_confidence);
+}
+
+/// [`taa_reproject_with`] taking its temporaries from `scratch`, so repeated
+/// calls stop allocating once the pool has warmed up. The result does not
+/// depend on the scratch contents.
+#[cfg(feature = "alloc")]
+pub fn taa_reproject_with_scratch(
+    frame: &TaaFrame<'_>,
+    params: &TaaParams,
+    out: &mut [f32],
+    out_confidence: &mut [f32],
+    scratch: &mut KernelScratch,
+) {
+    if let Err(err) = check_confidence(frame, params, out.len(), out_confidence.len()) {
+        panic!("{}", err);
+    }
+    resolve_with_scratch(frame, params, out, out_confidence, scratch);
+}
+
+/// Fallible [`taa_reproject_with`]: returns an error instead of panicking on
+/// mismatched buffers, inconsistent settings, or a non-finite blend,
+/// sharpness, velocity weight or jitter.
+pub fn taa_reproject_with_checked(
+    frame: &TaaFrame<'_>,
+    params: &TaaParams,
+    out: &mut [f32],
+    out_confidence: &mut [f32],
+) -> Result<(), KernelError> {
+    check_confidence(frame, params, out.len(), out_confidence.len())?;
+    check_finite("blend", params.blend)?;
+    for blend in params.channel_blend.iter().flatten() {
+        check_finite("channel blend", *blend)?;
+    }
+    check_finite("sharpness", params.sharpness)?;
+    check_finite("velocity weight", params.velocity_weight)?;
+    check_finite("jitter", frame.jitter.0)?;
+    check_finite("jitter", frame.jitter.1)?;
+    resolve(frame, params, out, out_confidence);
+    Ok(())
+}
+
+/// [`taa_reproject_with`] that also reports where the resolve discarded the
+/// history, for visualising the rejection tests. For every pixel `out_debug`
+/// receives `channels + 1` floats: the reprojected history as fetched (the
+/// value the resolve blends when it keeps it), then `1.0` if the history was
+/// rejected (off screen, disoccluded, failing the depth test, or more than
+/// `rejection_threshold` outside the current 3x3 neighbourhood's range) or
+/// `0.0` if it was kept. The resolve never clamps history into the
+/// neighbourhood, in RGB or YCoCg, so there is no clamped colour to report.
+/// An empty `out_debug` skips the extra pass.
+pub fn taa_reproject_debug(
+    frame: &TaaFrame<'_>,
+    params: &TaaParams,
+    out: &mut [f32],
+    out_confidence: &mut [f32],
+    out_debug: &mut [f32],
+) {
+    let checked = check_confidence(frame, params, out.len(), out_confidence.len()).and_then(|()| {
+        if out_debug.is_empty() {
+            return Ok(());
+        }
+        let pixels = frame.width * frame.height;
+        check_len("debug", out_debug.len(), pixels * (frame.channels + 1))
+    });
+    if let Err(err) = checked {
+        panic!("{}", err);
+    }
+    resolve(frame, params, out, out_confidence);
+    if !out_debug.is_empty() {
+        write_rejection_debug(frame, params, out_debug);
+    }
+}
+
+/// Fills the debug buffer of [`taa_reproject_debug`].
+fn write_rejection_debug(frame: &TaaFrame<'_>, params: &TaaParams, out_debug: &mut [f32]) {
+    let (w, channels) = (frame.width, frame.channels);
+    for (idx, pixel) in out_debug.chunks_exact_mut(channels + 1).enumerate() {
+        let (x, y) = (idx % w, idx / w);
+        let reprojection = reproject(frame, params, x, y);
+        pixel[..channels].copy_from_slice(&reprojection.history[..channels]);
+        pixel[channels] = if history_rejected(frame, params, x, y, &reprojection) {
+            1.0
+        } else {
+            0.0
+        };
+    }
+}
+
+/// [`taa_reproject_with`] as a [`Kernel`]: the input is the current frame,
+/// everything else comes from `frame`, whose own `curr` is ignored.
+#[derive(Clone, Copy, Debug)]
+pub struct TaaKernel<'a> {
+    pub frame: TaaFrame<'a>,
+}
+
+impl<'a> TaaKernel<'a> {
+    pub fn new(frame: TaaFrame<'a>) -> Self {
+        TaaKernel { frame }
+    }
+}
+
+impl Kernel for TaaKernel<'_> {
+    type Params = TaaParams;
+
+    fn apply(&self, input: &[f32], params: &TaaParams, out: &mut [f32]) {
+        let frame = TaaFrame {
+            curr: input,
+            ..self.frame
+        };
+        taa_reproject_with(&frame, params, out, &mut []);
+    }
+}
+
+/// [`taa_reproject_with`] for half-float frames such as RGBA16F render
+/// targets. `curr`, `prev` and `out` hold raw IEEE half bits; they are widened
+/// to `f32` for the resolve and the result is rounded back. `channels` (3 or
+/// 4) is the interleaved channel count, as for [`TaaFrame::with_channels`].
+#[cfg(all(feature = "half", feature = "alloc"))]
+#[allow(clippy::too_many_arguments)]
+pub fn taa_reproject_f16(
+    curr: &[u16],
+    prev: &[u16],
+    motion: &[f32],
+    w: usize,
+    h: usize,
+    channels: usize,
+    params: &TaaParams,
+    out: &mut [u16],
+) {
+    if let Err(err) = reproject_f16(curr, prev, motion, w, h, channels, params, out) {
+        panic!("{}", err);
+    }
+}
+
+#[cfg(all(feature = "half", feature = "alloc"))]
+#[allow(clippy::too_many_arguments)]
+fn reproject_f16(
+    curr: &[u16],
+    prev: &[u16],
+    motion: &[f32],
+    w: usize,
+    h: usize,
+    channels: usize,
+    params: &TaaParams,
+    out: &mut [u16],
+) -> Result<(), KernelError> {
+    let mut curr32 = vec![0.0; curr.len()];
+    let mut prev32 = vec![0.0; prev.len()];
+    let mut out32 = vec![0.0; out.len()];
+    utils::f16_slice_to_f32(curr, &mut curr32)?;
+    utils::f16_slice_to_f32(prev, &mut prev32)?;
+
+    let frame = TaaFrame::new(&curr32, &prev32, motion, w, h).with_channels(channels);
+    check(&frame, params, out32.len())?;
+    resolve(&frame, params, &mut out32, &mut []);
+    utils::f32_slice_to_f16(&out32, out)
+}
+
+/// [`check`] plus the optional per-pixel confidence buffer.
+fn check_confidence(
+    frame: &TaaFrame<'_>,
+    params: &TaaParams,
+    out_len: usize,
+    confidence_len: usize,
+) -> Result<(), KernelError> {
+    check(frame, params, out_len)?;
+    if confidence_len != 0 {
+        check_len("confidence", confidence_len, frame.width * frame.height)?;
+    }
+    Ok(())
+}
+
+/// Resolves a validated frame into `out`.
+fn resolve(frame: &TaaFrame<'_>, params: &TaaParams, out: &mut [f32], out_confidence: &mut [f32]) {
+    #[cfg(feature = "alloc")]
+    resolve_with_scratch(
+        frame,
+        params,
+        out,
+        out_confidence,
+        &mut KernelScratch::new(),
+    );
+    #[cfg(not(feature = "alloc"))]
+    blend_frame(frame, params, out, out_confidence);
+}
+
+/// [`resolve`] taking sharpening temporaries from `scratch`.
+#[cfg(feature = "alloc")]
+fn resolve_with_scratch(
+    frame: &TaaFrame<'_>,
+    params: &TaaParams,
+    out: &mut [f32],
+    out_confidence: &mut [f32],
+    scratch: &mut KernelScratch,
+) {
+    blend_frame(frame, params, out, out_confidence);
+    if params.sharpness > 0.0 && !out.is_empty() {
+        if frame.planar {
+            // Sharpening never mixes channels, so each plane is sharpened as
+            // a single-channel image.
+            for plane in out.chunks_exact_mut(frame.width * frame.height) {
+                let (w, h) = (frame.width, frame.height);
+                sharpen(plane, w, w, h, 1, params.sharpness, scratch);
+            }
+        } else {
+            sharpen(
+                out,
+                frame.out_stride,
+                frame.width,
+                frame.height,
+                frame.channels,
+                params.sharpness,
+                scratch,
+            );
+        }
+    }
+}
+
+/// Blends history into `out` for a validated frame, without sharpening.
+fn blend_frame(
+    frame: &TaaFrame<'_>,
+    params: &TaaParams,
+    out: &mut [f32],
+    out_confidence: &mut [f32],
+) {
+    if out.is_empty() {
+        return;
+    }
+
+    if is_plain_lerp(frame, params) {
+        let blend = params.blend.clamp(0.0, 1.0);
+        lerp(frame.curr, frame.prev, blend, out);
+        out_confidence.fill(blend);
+    } else if frame.planar {
+        // `check` rejects planar frames without `alloc`.
+        #[cfg(feature = "alloc")]
+        resolve_rows_planar(frame, params, out, out_confidence);
+    } else {
+        resolve_rows(frame, params, out, out_confidence);
+    }
+}
+
+/// Resolves every row of the frame, in parallel tiles when `rayon` is enabled.
+fn resolve_rows(
+    frame: &TaaFrame<'_>,
+    params: &TaaParams,
+    out: &mut [f32],
+    out_confidence: &mut [f32],
+) {
+    let stride = frame.out_stride;
+
+    #[cfg(all(feature = "rayon", not(target_arch = "wasm32")))]
+    {
+        use rayon::prelude::*;
+
+        let resolve_tile = |tile: usize, chunk: &mut [f32], conf_chunk: &mut [f32]| {
+            for (row, out_row) in chunk.chunks_mut(stride).enumerate() {
+                let conf_row = confidence_row(conf_chunk, row, frame.width);
+                resolve_row(frame, params, tile * TILE_ROWS + row, out_row, conf_row);
+            }
+        };
+        crate::pool::install(|| {
+            let tiles = out.par_chunks_mut(TILE_ROWS * stride).enumerate();
+            if out_confidence.is_empty() {
+                tiles.for_each(|(tile, chunk)| resolve_tile(tile, chunk, &mut []));
+            } else {
+                tiles
+                    .zip(out_confidence.par_chunks_mut(TILE_ROWS * frame.width))
+                    .for_each(|((tile, chunk), conf_chunk)| resolve_tile(tile, chunk, conf_chunk));
+            }
+        });
+    }
+
+    #[cfg(not(all(feature = "rayon", not(target_arch = "wasm32"))))]
+    for (y, out_row) in out.chunks_mut(stride).enumerate() {
+        let conf_row = confidence_row(out_confidence, y, frame.width);
+        resolve_row(frame, params, y, out_row, conf_row);
+    }
+}
+
+/// [`resolve_rows`] for planar frames. Each row is resolved into a staging
+/// row of interleaved pixels and scattered into the planes, so no full-frame
+/// copy is needed.
+#[cfg(feature = "alloc")]
+fn resolve_rows_planar(
+    frame: &TaaFrame<'_>,
+    params: &TaaParams,
+    out: &mut [f32],
+    out_confidence: &mut [f32],
+) {
+    let (w, h, channels) = (frame.width, frame.height, frame.channels);
+
+    // Resolves the rows starting at `first_row` covered by `planes`, which
+    // hold the same rows of every output plane.
+    let resolve_tile = |first_row: usize, planes: &mut [&mut [f32]], conf_chunk: &mut [f32]| {
+        let mut staging = vec![0.0; w * channels];
+        for row in 0..planes[0].len() / w {
+            let conf_row = confidence_row(conf_chunk, row, w);
+            resolve_row(frame, params, first_row + row, &mut staging, conf_row);
+            for (x, pixel) in staging.chunks_exact(channels).enumerate() {
+                for (plane, &value) in planes.iter_mut().zip(pixel) {
+                    plane[row * w + x] = value;
+                }
+            }
+        }
+    };
+
+    #[cfg(all(feature = "rayon", not(target_arch = "wasm32")))]
+    {
+        use rayon::prelude::*;
+
+        let tile_len = TILE_ROWS * w;
+        let mut tiles: Vec<(usize, Vec<&mut [f32]>)> = (0..h.div_ceil(TILE_ROWS))
+            .map(|tile| (tile * TILE_ROWS, Vec::with_capacity(channels)))
+            .collect();
+        for plane in out.chunks_exact_mut(w * h) {
+            for (tile, chunk) in tiles.iter_mut().zip(plane.chunks_mut(tile_len)) {
+                tile.1.push(chunk);
+            }
+        }
+        crate::pool::install(|| {
+            if out_confidence.is_empty() {
+                tiles.into_par_iter().for_each(|(first_row, mut planes)| {
+                    resolve_tile(first_row, &mut planes, &mut [])
+                });
+            } else {
+                tiles
+                    .into_par_iter()
+                    .zip(out_confidence.par_chunks_mut(tile_len))
+                    .for_each(|((first_row, mut planes), conf_chunk)| {
+                        resolve_tile(first_row, &mut planes, conf_chunk)
+                    });
+            }
+        });
+    }
+
+    #[cfg(not(all(feature = "rayon", not(target_arch = "wasm32"))))]
+    {
+        let mut planes: Vec<&mut [f32]> = out.chunks_exact_mut(w * h).collect();
+        resolve_tile(0, &mut planes, out_confidence);
+    }
+}
+
+/// Sharpens an interleaved buffer in place with a cross-shaped unsharp mask.
+/// Border taps are clamped to the image; two rows of the unsharpened image are
+/// kept so every pixel sees its original neighbours.
+#[cfg(feature = "alloc")]
+fn sharpen(
+    buf: &mut [f32],
+    stride: usize,
+    w: usize,
+    h: usize,
+    channels: usize,
+    amount: f32,
+    scratch: &mut KernelScratch,
+) {
+    let row_len = w * channels;
+    let mut above = scratch.take_copy(&buf[..row_len]);
+    let mut centre = scratch.take_copy(&above);
+    let mut below = scratch.take_copy(&above);
+
+    for y in 0..h {
+        if y + 1 < h {
+            below.copy_from_slice(&buf[(y + 1) * stride..(y + 1) * stride + row_len]);
+        } else {
+            below.copy_from_slice(&centre);
+        }
+
+        let row = &mut buf[y * stride..y * stride + row_len];
+        for x in 0..w {
+            let left = x.saturating_sub(1) * channels;
+            let right = (x + 1).min(w - 1) * channels;
+            for c in 0..channels {
+                let i = x * channels + c;
+                let taps = [above[i], below[i], centre[left + c], centre[right + c]];
+                let mean = taps.iter().sum::<f32>() * 0.25;
+                let lo = taps.iter().fold(centre[i], |a, &b| a.min(b));
+                let hi = taps.iter().fold(centre[i], |a, &b| a.max(b));
+                // max/min rather than clamp: NaN taps must not panic.
+                row[i] = (centre[i] + amount * (centre[i] - mean)).max(lo).min(hi);
+            }
+        }
+
+        core::mem::swap(&mut above, &mut centre);
+        core::mem::swap(&mut centre, &mut below);
+    }
+
+    scratch.give(above);
+    scratch.give(centre);
+    scratch.give(below);
+}
+
+/// Row `row` of an optional per-pixel buffer; empty when the buffer is.
+fn confidence_row(buf: &mut [f32], row: usize, width: usize) -> &mut [f32] {
+    if buf.is_empty() {
+        buf
+    } else {
+        &mut buf[row * width..(row + 1) * width]
+    }
+}
+
+/// TAA resolve that writes the new history over the previous frame instead of
+/// into a third buffer. `frame.prev` is ignored: the previous frame is read
+/// from `history`, which receives the result, so `frame.prev_stride` and
+/// `frame.out_stride` must agree.
+///
+/// Resolved rows are held back in a small ring until no later row can still
+/// fetch the original texels under them, so the output matches
+/// [`taa_reproject_with`] exactly. The ring spans the largest vertical motion
+/// in the frame plus the sampling filter footprint.
+#[cfg(feature = "alloc")]
+pub fn taa_reproject_in_place(frame: &TaaFrame<'_>, params: &TaaParams, history: &mut [f32]) {
+    taa_reproject_in_place_with_scratch(frame, params, history, &mut KernelScratch::new());
+}
+
+/// [`taa_reproject_in_place`] taking its row ring and sharpening rows from
+/// `scratch` instead of allocating them per call.
+#[cfg(feature = "alloc")]
+pub fn taa_reproject_in_place_with_scratch(
+    frame: &TaaFrame<'_>,
+    params: &TaaParams,
+    history: &mut [f32],
+    scratch: &mut KernelScratch,
+) {
+    let (w, h) = (frame.width, frame.height);
+    assert!(
+        frame.prev_stride == frame.out_stride,
+        "in-place resolve needs matching history strides, got {} and {}",
+        frame.prev_stride,
+        frame.out_stride
+    );
+    assert!(!frame.planar, "in-place resolve needs an interleaved frame");
+    validate(
+        &TaaFrame {
+            prev: history,
+            ..*frame
+        },
+        params,
+        history.len(),
+    );
+    if history.is_empty() {
+        return;
+    }
+
+    if is_plain_lerp(frame, params) {
+        let blend = params.blend.clamp(0.0, 1.0);
+        let inv_blend = 1.0 - blend;
+        for (o, &c) in history.iter_mut().zip(frame.curr) {
+            *o = c * inv_blend + *o * blend;
+        }
+    } else {
+        resolve_rows_in_place(frame, params, history, scratch);
+    }
+
+    if params.sharpness > 0.0 {
+        sharpen(
+            history,
+            frame.out_stride,
+            w,
+            h,
+            frame.channels,
+            params.sharpness,
+            scratch,
+        );
+    }
+}
+
+#[cfg(feature = "alloc")]
+fn resolve_rows_in_place(
+    frame: &TaaFrame<'_>,
+    params: &TaaParams,
+    history: &mut [f32],
+    scratch: &mut KernelScratch,
+) {
+    let (w, h) = (frame.width, frame.height);
+    let max_dy = frame
+        .motion
+        .chunks_exact(2)
+        .map(|mv| mv[1].abs())
+        .fold(0.0_f32, f32::max)
+        .min(params.max_motion);
+    // Catmull-Rom reaches two rows past the sample position, bilinear one.
+    let reach = ((max_dy.ceil() as usize).saturating_add(2)).min(h);
+    let slots = reach + 1;
+    let row_len = w * frame.channels;
+    let mut pending = scratch.take(slots * row_len, 0.0);
+
+    let stride = frame.out_stride;
+    let flush = |history: &mut [f32], pending: &[f32], row: usize| {
+        let slot = (row % slots) * row_len;
+        history[row * stride..row * stride + row_len]
+            .copy_from_slice(&pending[slot..slot + row_len]);
+    };
+
+    for y in 0..h {
+        let slot = (y % slots) * row_len;
+        let frame = TaaFrame {
+            prev: history,
+            ..*frame
+        };
+        resolve_row(
+            &frame,
+            params,
+            y,
+            &mut pending[slot..slot + row_len],
+            &mut [],
+        );
+        if y >= reach {
+            flush(history, &pending, y - reach);
+        }
+    }
+    for row in h.saturating_sub(reach)..h {
+        flush(history, &pending, row);
+    }
+    scratch.give(pending);
+}
+
+/// Stateful TAA resolver that owns the history buffer, so callers only hand
+/// over the current frame instead of double-buffering `prev`/`out` themselves.
+#[cfg(feature = "alloc")]
+#[derive(Clone, Debug)]
+pub struct TaaContext {
+    width: usize,
+    height: usize,
+    channels: usize,
+    params: TaaParams,
+    history: Vec<f32>,
+    /// Output of the next resolve; swapped with `history` once it is written.
+    resolved: Vec<f32>,
+    temporaries: KernelScratch,
+    has_history: bool,
+}
+
+#[cfg(feature = "alloc")]
+impl TaaContext {
+    pub fn new(width: usize, height: usize, params: TaaParams) -> Self {
+        TaaContext {
+            width,
+            height,
+            channels: 3,
+            params,
+            history: Vec::new(),
+            resolved: Vec::new(),
+            temporaries: KernelScratch::new(),
+            has_history: false,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Resolves frames of `channels` floats per pixel; see
+    /// [`TaaFrame::with_channels`]. Discards any stored history.
+    pub fn with_channels(mut self, channels: usize) -> Self {
+        self.channels = channels;
+        self.has_history = false;
+        self
+    }
+
+    pub fn channels(&self) -> usize {
+        self.channels
+    }
+
+    pub fn params(&self) -> &TaaParams {
+        &self.params
+    }
+
+    pub fn set_params(&mut self, params: TaaParams) {
+        self.params = params;
+    }
+
+    /// Whether a previous frame is available to blend against.
+    pub fn has_history(&self) -> bool {
+        self.has_history
+    }
+
+    /// Discards the history, e.g. on a scene cut. The next submitted frame is
+    /// passed through unchanged instead of ghosting against stale data.
+    pub fn reset(&mut self) {
+        self.has_history = false;
+    }
+
+    /// Blends `curr` against the stored history, keeps the result as the new
+    /// history and returns it. Without valid history (the first frame, or the
+    /// first one after [`reset`](Self::reset)) `curr` passes straight through.
+    pub fn submit(&mut self, curr: &[f32], motion: &[f32]) -> &[f32] {
+        if !self.has_history {
+            let frame = TaaFrame::new(curr, curr, motion, self.width, self.height)
+                .with_channels(self.channels);
+            validate(&frame, &self.params, curr.len());
+            self.history.clear();
+            self.history.extend_from_slice(curr);
+            self.has_history = true;
+            return &self.history;
+        }
+
+        self.resolved.resize(self.history.len(), 0.0);
+        let frame = TaaFrame::new(curr, &self.history, motion, self.width, self.height)
+            .with_channels(self.channels);
+        taa_reproject_with_scratch(
+            &frame,
+            &self.params,
+            &mut self.resolved,
+            &mut [],
+            &mut self.temporaries,
+        );
+        core::mem::swap(&mut self.history, &mut self.resolved);
+        &self.history
+    }
+}
+
+/// Inputs for [`taa_upscale`]: a low-resolution current frame accumulated
+/// into a high-resolution history.
+///
+/// `curr` holds `width * height` RGB triples rendered with a sub-pixel
+/// projection offset of `(jitter_x, jitter_y)` low-resolution pixels (each in
+/// `[-0.5, 0.5]`). `prev` holds `out_width * out_height` RGB triples and
+/// `motion` (optional) one `(dx, dy)` pair per high-resolution pixel.
+#[derive(Clone, Copy, Debug)]
+pub struct TaaUpscaleFrame<'a> {
+    pub curr: &'a [f32],
+    pub prev: &'a [f32],
+    pub motion: &'a [f32],
+    pub width: usize,
+    pub height: usize,
+    pub out_width: usize,
+    pub out_height: usize,
+    pub jitter_x: f32,
+    pub jitter_y: f32,
+}
+
+/// Temporal upscaling. Each high-resolution pixel reprojects its history and
+/// blends in the nearest jittered low-resolution sample, weighted by how close
+/// that sample landed to the pixel centre. Over a jitter sequence that covers
+/// the sub-pixel grid the output converges to the full-resolution image.
+pub fn taa_upscale(frame: &TaaUpscaleFrame<'_>, blend: f32, out: &mut [f32]) {
+    let (w, h) = (frame.width, frame.height);
+    let (out_w, out_h) = (frame.out_width, frame.out_height);
+    let low_len = w
         .checked_mul(h)
+        .and_then(|n| n.checked_mul(3))
+        .expect("image dimensions overflow when computing RGB buffer length");
+    let high_pixels = out_w
+        .checked_mul(out_h)
         .expect("image dimensions overflow when computing pixel count");
-    let expected_rgb_len = pixel_count
+    let high_len = high_pixels
         .checked_mul(3)
         .expect("pixel count overflow when computing RGB buffer length");
 
     assert!(
-        curr.len() == expected_rgb_len,
+        frame.curr.len() == low_len,
         "current buffer length {} does not match expected {}",
-        curr.len(),
-        expected_rgb_len
+        frame.curr.len(),
+        low_len
     );
     assert!(
-        prev.len() == expected_rgb_len,
+        frame.prev.len() == high_len,
         "previous buffer length {} does not match expected {}",
-        prev.len(),
-        expected_rgb_len
+        frame.prev.len(),
+        high_len
     );
     assert!(
-        out.len() == expected_rgb_len,
+        out.len() == high_len,
         "output buffer length {} does not match expected {}",
         out.len(),
-        expected_rgb_len
+        high_len
+    );
+    assert!(
+        frame.motion.is_empty() || frame.motion.len() == high_pixels * 2,
+        "motion buffer length {} does not match expected {}",
+        frame.motion.len(),
+        high_pixels * 2
+    );
+    if low_len == 0 || high_len == 0 {
+        return;
+    }
+
+    let blend = blend.clamp(0.0, 1.0);
+    let scale_x = out_w as f32 / w as f32;
+    let scale_y = out_h as f32 / h as f32;
+
+    for y in 0..out_h {
+        for x in 0..out_w {
+            let idx = y * out_w + x;
+            let (mx, my) = if frame.motion.is_empty() {
+                (0.0, 0.0)
+            } else {
+                (frame.motion[idx * 2], frame.motion[idx * 2 + 1])
+            };
+            let hx = x as f32 - mx;
+            let hy = y as f32 - my;
+            let on_screen =
+                hx >= -0.5 && hx <= out_w as f32 - 0.5 && hy >= -0.5 && hy <= out_h as f32 - 0.5;
+            let history = sample_bilinear(frame.prev, out_w * 3, out_w, out_h, 3, hx, hy, false);
+
+            // Nearest jittered low-resolution sample and its high-res position.
+            let lx = (x as f32 + 0.5) / scale_x - 0.5 - frame.jitter_x;
+            let ly = (y as f32 + 0.5) / scale_y - 0.5 - frame.jitter_y;
+            let sx = (lx.round().max(0.0) as usize).min(w - 1);
+            let sy = (ly.round().max(0.0) as usize).min(h - 1);
+            let dx = (sx as f32 + 0.5 + frame.jitter_x) * scale_x - 0.5 - x as f32;
+            let dy = (sy as f32 + 0.5 + frame.jitter_y) * scale_y - 0.5 - y as f32;
+            let coverage = (-(dx * dx + dy * dy) / (2.0 * UPSCALE_SIGMA * UPSCALE_SIGMA)).exp();
+
+            let current_weight = if on_screen {
+                (1.0 - blend) * coverage
+            } else {
+                1.0
+            };
+            let base = (sy * w + sx) * 3;
+            for c in 0..3 {
+                out[idx * 3 + c] =
+                    history[c] + (frame.curr[base + c] - history[c]) * current_weight;
+            }
+        }
+    }
+}
+
+/// Width, in high-resolution pixels, of the Gaussian that decides how much a
+/// jittered sample contributes to a nearby output pixel.
+const UPSCALE_SIGMA: f32 = 0.25;
+
+/// Without motion, rejection or per-sample fixups every pixel blends against
+/// the history texel at the same index with the same weight: a plain lerp.
+fn is_plain_lerp(frame: &TaaFrame<'_>, params: &TaaParams) -> bool {
+    frame.motion.is_empty()
+        && frame.is_tightly_packed()
+        && frame.curr_depth.is_empty()
+        && !params.rejection_threshold.is_finite()
+        && !params.sanitize
+        && !params.hdr
+        && !params.decode_srgb
+        && !params.flicker_rate.is_finite()
+        && params.channel_blend.is_none()
+        && frame.jitter == (0.0, 0.0)
+}
+
+/// Rows per parallel work item. Large enough to amortise scheduling, small
+/// enough to balance a 4K frame across cores.
+#[cfg(all(feature = "rayon", not(target_arch = "wasm32")))]
+const TILE_ROWS: usize = 16;
+
+/// Resolves row `y` of the frame into `out_row`. Reads `prev` anywhere in the
+/// image but only writes the given row, so rows can be processed in parallel.
+fn resolve_row(
+    frame: &TaaFrame<'_>,
+    params: &TaaParams,
+    y: usize,
+    out_row: &mut [f32],
+    conf_row: &mut [f32],
+) {
+    let (w, h, channels) = (frame.width, frame.height, frame.channels);
+    let blends = match params.channel_blend {
+        Some(blends) => blends.map(|b| b.clamp(0.0, 1.0)),
+        None => [params.blend.clamp(0.0, 1.0); 4],
+    };
+
+    let curr_texels = frame.texels(frame.curr_stride);
+    for x in 0..w {
+        let reprojection = reproject(frame, params, x, y);
+        let history = reprojection.history;
+
+        // Fraction of each channel's blend that survives velocity weighting
+        // and the rejection tests.
+        let trust = if history_rejected(frame, params, x, y, &reprojection) {
+            0.0
+        } else {
+            (-params.velocity_weight * reprojection.speed).exp()
+        };
+        let weights = blends.map(|b| b * trust);
+
+        if !conf_row.is_empty() {
+            conf_row[x] = weights[0];
+        }
+
+        let mut current = [0.0_f32; 4];
+        if frame.jitter == (0.0, 0.0) {
+            for (c, value) in current.iter_mut().enumerate().take(channels) {
+                *value = load(frame.curr, curr_texels.index(x, y, c), params.sanitize);
+            }
+        } else {
+            let (jx, jy) = frame.jitter;
+            current = sample_bilinear_at(
+                frame.curr,
+                curr_texels,
+                w,
+                h,
+                channels,
+                x as f32 - jx,
+                y as f32 - jy,
+                params.sanitize,
+            );
+        }
+        if params.flicker_rate.is_finite() && weights[..3].iter().any(|&w| w > 0.0) {
+            current = limit_luminance_change(current, history, params.flicker_rate);
+        }
+
+        for (c, &current) in current.iter().enumerate().take(channels) {
+            let weight = weights[c];
+            let inv_weight = 1.0 - weight;
+            let (current, hist) = if params.decode_srgb && c < 3 {
+                (
+                    utils::srgb_to_linear(current),
+                    utils::srgb_to_linear(history[c]),
+                )
+            } else {
+                (current, history[c])
+            };
+            let mut value = if params.hdr && c < 3 {
+                utils::inverse_tonemap(
+                    utils::tonemap(current) * inv_weight + utils::tonemap(hist) * weight,
+                )
+            } else {
+                current * inv_weight + hist * weight
+            };
+            if params.decode_srgb && c < 3 {
+                value = utils::linear_to_srgb(value);
+            }
+            out_row[x * channels + c] = if params.sanitize {
+                utils::clamp_nan(value, 0.0)
+            } else {
+                value
+            };
+        }
+    }
+}
+
+/// Whether the resolve discards the history `reprojection` fetched for pixel
+/// `(x, y)`: it falls off screen, moved faster than the disocclusion
+/// threshold, fails the depth test, or lies more than `rejection_threshold`
+/// outside the range of the current 3x3 neighbourhood.
+fn history_rejected(
+    frame: &TaaFrame<'_>,
+    params: &TaaParams,
+    x: usize,
+    y: usize,
+    reprojection: &Reprojection,
+) -> bool {
+    let (w, h, channels) = (frame.width, frame.height, frame.channels);
+    if !reprojection.on_screen
+        || reprojection.speed > params.disocclusion_threshold
+        || depth_rejected(frame, params, y * w + x, reprojection.hx, reprojection.hy)
+    {
+        return true;
+    }
+    if !params.rejection_threshold.is_finite() {
+        return false;
+    }
+    let (lo, hi) = neighbourhood_range(
+        frame.curr,
+        frame.texels(frame.curr_stride),
+        w,
+        h,
+        channels,
+        x,
+        y,
+        params.sanitize,
     );
+    let history = &reprojection.history;
+    let outside = (0..channels)
+        .map(|c| (lo[c] - history[c]).max(history[c] - hi[c]))
+        .fold(0.0_f32, f32::max);
+    outside > params.rejection_threshold
+}
+
+/// Where pixel `(x, y)` came from in the previous frame and the history
+/// fetched there.
+struct Reprojection {
+    history: [f32; 4],
+    hx: f32,
+    hy: f32,
+    /// Length of the (possibly dilated) motion vector, before `max_motion`.
+    speed: f32,
+    on_screen: bool,
+}
+
+/// Follows pixel `(x, y)`'s motion vector back into `prev` and samples the
+/// history there with `params.sampling`.
+fn reproject(frame: &TaaFrame<'_>, params: &TaaParams, x: usize, y: usize) -> Reprojection {
+    let (w, h, channels) = (frame.width, frame.height, frame.channels);
+    let idx = y * w + x;
+    let (mut mx, mut my) = if params.dilate_motion {
+        frame.dilated_motion_at(x, y)
+    } else {
+        frame.motion_at(idx)
+    };
+    if params.sanitize {
+        mx = utils::clamp_nan(mx, 0.0);
+        my = utils::clamp_nan(my, 0.0);
+    }
+    let speed = (mx * mx + my * my).sqrt();
+    let fetch_scale = if speed > params.max_motion {
+        params.max_motion / speed
+    } else {
+        1.0
+    };
+    let hx = x as f32 - mx * fetch_scale;
+    let hy = y as f32 - my * fetch_scale;
+
+    let on_screen = hx >= -0.5 && hx <= w as f32 - 0.5 && hy >= -0.5 && hy <= h as f32 - 0.5;
+    let history = match params.sampling {
+        HistorySampling::Bilinear => sample_bilinear_at(
+            frame.prev,
+            frame.texels(frame.prev_stride),
+            w,
+            h,
+            channels,
+            hx,
+            hy,
+            params.sanitize,
+        ),
+        HistorySampling::CatmullRom => sample_catmull_rom(
+            frame.prev,
+            frame.texels(frame.prev_stride),
+            w,
+            h,
+            channels,
+            hx,
+            hy,
+            params.sanitize,
+        ),
+    };
+    Reprojection {
+        history,
+        hx,
+        hy,
+        speed,
+        on_screen,
+    }
+}
+
+/// Rescales the colour of `current` so its luminance lies within `rate`
+/// (relative) of the history luminance, preserving chroma. Alpha is untouched.
+fn limit_luminance_change(current: [f32; 4], history: [f32; 4], rate: f32) -> [f32; 4] {
+    let curr_luma = utils::luminance([current[0], current[1], current[2]]);
+    let hist_luma = utils::luminance([history[0], history[1], history[2]]);
+    let rate = rate.max(0.0);
+    let (lo, hi) = (hist_luma * (1.0 - rate), hist_luma * (1.0 + rate));
+    let target = curr_luma.max(lo.min(hi)).min(lo.max(hi));
+    if curr_luma > f32::EPSILON {
+        let scale = target / curr_luma;
+        [
+            current[0] * scale,
+            current[1] * scale,
+            current[2] * scale,
+            current[3],
+        ]
+    } else {
+        [target, target, target, current[3]]
+    }
+}
+
+/// `out = curr * (1 - blend) + prev * blend`, element-wise.
+#[cfg(not(feature = "simd"))]
+fn lerp(curr: &[f32], prev: &[f32], blend: f32, out: &mut [f32]) {
+    let inv_blend = 1.0 - blend;
+    for ((o, &c), &p) in out.iter_mut().zip(curr).zip(prev) {
+        *o = c * inv_blend + p * blend;
+    }
+}
+
+/// `out = curr * (1 - blend) + prev * blend`, eight lanes at a time with a
+/// scalar tail.
+#[cfg(feature = "simd")]
+fn lerp(curr: &[f32], prev: &[f32], blend: f32, out: &mut [f32]) {
+    use wide::f32x8;
+
+    let inv_blend = 1.0 - blend;
+    let blend_v = f32x8::splat(blend);
+    let inv_blend_v = f32x8::splat(inv_blend);
 
-    if !motion.is_empty() {
+    let mut out_chunks = out.chunks_exact_mut(8);
+    let mut curr_chunks = curr.chunks_exact(8);
+    let mut prev_chunks = prev.chunks_exact(8);
+    for ((o, c), p) in (&mut out_chunks)
+        .zip(&mut curr_chunks)
+        .zip(&mut prev_chunks)
+    {
+        let c = f32x8::from(<[f32; 8]>::try_from(c).unwrap());
+        let p = f32x8::from(<[f32; 8]>::try_from(p).unwrap());
+        o.copy_from_slice(&(c * inv_blend_v + p * blend_v).to_array());
+    }
+
+    let tail = out_chunks.into_remainder();
+    for ((o, &c), &p) in tail
+        .iter_mut()
+        .zip(curr_chunks.remainder())
+        .zip(prev_chunks.remainder())
+    {
+        *o = c * inv_blend + p * blend;
+    }
+}
+
+/// Panicking form of [`check`], used by the infallible entry points.
+#[cfg(feature = "alloc")]
+fn validate(frame: &TaaFrame<'_>, params: &TaaParams, out_len: usize) {
+    if let Err(err) = check(frame, params, out_len) {
+        panic!("{}", err);
+    }
+}
+
+fn check(frame: &TaaFrame<'_>, params: &TaaParams, out_len: usize) -> Result<(), KernelError> {
+    if frame.channels != 3 && frame.channels != 4 {
+        return Err(KernelError::InvalidArgument(
+            "unsupported channel count, expected 3 or 4",
+        ));
+    }
+    let pixel_count = frame
+        .width
+        .checked_mul(frame.height)
+        .ok_or(KernelError::DimensionOverflow)?;
+    let row_len = frame
+        .width
+        .checked_mul(frame.channels)
+        .ok_or(KernelError::DimensionOverflow)?;
+    if frame.planar {
+        if frame.curr_stride != row_len
+            || frame.prev_stride != row_len
+            || frame.out_stride != row_len
+        {
+            return Err(KernelError::InvalidArgument(
+                "planar frames cannot have row padding",
+            ));
+        }
+        if cfg!(not(feature = "alloc")) {
+            return Err(KernelError::InvalidArgument(
+                "planar frames require the alloc feature",
+            ));
+        }
+    }
+
+    let checks = [
+        ("current", frame.curr.len(), frame.curr_stride),
+        ("previous", frame.prev.len(), frame.prev_stride),
+        ("output", out_len, frame.out_stride),
+    ];
+    for (name, len, stride) in checks {
+        if stride < row_len {
+            return Err(KernelError::InvalidArgument(match name {
+                "current" => "current row stride is shorter than a row",
+                "previous" => "previous row stride is shorter than a row",
+                _ => "output row stride is shorter than a row",
+            }));
+        }
+        let expected_len =
+            strided_len(stride, row_len, frame.height).ok_or(KernelError::DimensionOverflow)?;
+        check_len(name, len, expected_len)?;
+    }
+
+    if !frame.motion.is_empty() {
         let expected_motion_len = pixel_count
             .checked_mul(2)
-            .expect("pixel count overflow when computing motion buffer length");
-        assert!(
-            motion.len() == expected_motion_len,
-            "motion buffer length {} does not match expected {}",
-            motion.len(),
-            expected_motion_len
-        );
+            .ok_or(KernelError::DimensionOverflow)?;
+        check_len("motion", frame.motion.len(), expected_motion_len)?;
     }
 
-    let blend = blend.clamp(0.0, 1.0);
-    let inv_blend = 1.0 - blend;
+    if !frame.curr_depth.is_empty() || !frame.prev_depth.is_empty() {
+        check_len("current depth", frame.curr_depth.len(), pixel_count)?;
+        check_len("previous depth", frame.prev_depth.len(), pixel_count)?;
+    }
+    if params.max_motion.is_nan() || params.max_motion < 0.0 {
+        return Err(KernelError::InvalidArgument(
+            "max_motion must not be negative or NaN",
+        ));
+    }
+    if params.dilate_motion && frame.curr_depth.is_empty() {
+        return Err(KernelError::InvalidArgument(
+            "motion dilation requires depth buffers",
+        ));
+    }
+    if cfg!(not(feature = "alloc")) && params.sharpness > 0.0 {
+        return Err(KernelError::InvalidArgument(
+            "sharpening requires the alloc feature",
+        ));
+    }
+    Ok(())
+}
+
+/// Length of a buffer holding `height` rows of `row_len` floats spaced
+/// `stride` apart; the last row needs no trailing padding.
+fn strided_len(stride: usize, row_len: usize, height: usize) -> Option<usize> {
+    match height {
+        0 => Some(0),
+        _ => stride.checked_mul(height - 1)?.checked_add(row_len),
+    }
+}
+
+/// Standard TAA depth test: history is occluded when the previous depth at
+/// the reprojected texel (nearest tap) disagrees with the current depth.
+fn depth_rejected(frame: &TaaFrame<'_>, params: &TaaParams, idx: usize, hx: f32, hy: f32) -> bool {
+    if frame.curr_depth.is_empty() {
+        return false;
+    }
+    let (w, h) = (frame.width, frame.height);
+    let px = (hx.round().max(0.0) as usize).min(w - 1);
+    let py = (hy.round().max(0.0) as usize).min(h - 1);
+    let prev_depth = frame.prev_depth[py * w + px];
+    (prev_depth - frame.curr_depth[idx]).abs() > params.depth_tolerance
+}
+
+#[inline]
+fn load(buf: &[f32], idx: usize, sanitize: bool) -> f32 {
+    if sanitize {
+        utils::clamp_nan(buf[idx], 0.0)
+    } else {
+        buf[idx]
+    }
+}
+
+/// Float offsets between the rows, pixels and channels of an image buffer.
+#[derive(Clone, Copy, Debug)]
+struct Texels {
+    row: usize,
+    pixel: usize,
+    channel: usize,
+}
+
+impl Texels {
+    fn interleaved(stride: usize, channels: usize) -> Self {
+        Texels {
+            row: stride,
+            pixel: channels,
+            channel: 1,
+        }
+    }
+
+    #[inline]
+    fn index(&self, x: usize, y: usize, c: usize) -> usize {
+        y * self.row + x * self.pixel + c * self.channel
+    }
+}
+
+/// Bilinearly samples an interleaved buffer at pixel-centre coordinates,
+/// clamping taps to the image border. Channels past `channels` are zero.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn sample_bilinear(
+    buf: &[f32],
+    stride: usize,
+    w: usize,
+    h: usize,
+    channels: usize,
+    x: f32,
+    y: f32,
+    sanitize: bool,
+) -> [f32; 4] {
+    let texels = Texels::interleaved(stride, channels);
+    sample_bilinear_at(buf, texels, w, h, channels, x, y, sanitize)
+}
+
+/// [`sample_bilinear`] for any texel layout.
+#[allow(clippy::too_many_arguments)]
+fn sample_bilinear_at(
+    buf: &[f32],
+    texels: Texels,
+    w: usize,
+    h: usize,
+    channels: usize,
+    x: f32,
+    y: f32,
+    sanitize: bool,
+) -> [f32; 4] {
+    let x = x.clamp(0.0, (w - 1) as f32);
+    let y = y.clamp(0.0, (h - 1) as f32);
+    let x0 = x.floor() as usize;
+    let y0 = y.floor() as usize;
+    let x1 = (x0 + 1).min(w - 1);
+    let y1 = (y0 + 1).min(h - 1);
+    let fx = x - x0 as f32;
+    let fy = y - y0 as f32;
 
-    for idx in 0..pixel_count {
-        let base = idx * 3;
-        out[base] = curr[base] * inv_blend + prev[base] * blend;
-        out[base + 1] = curr[base + 1] * inv_blend + prev[base + 1] * blend;
-        out[base + 2] = curr[base + 2] * inv_blend + prev[base + 2] * blend;
+    let mut pixel = [0.0_f32; 4];
+    for (c, value) in pixel.iter_mut().enumerate().take(channels) {
+        let p00 = load(buf, texels.index(x0, y0, c), sanitize);
+        let p10 = load(buf, texels.index(x1, y0, c), sanitize);
+        let p01 = load(buf, texels.index(x0, y1, c), sanitize);
+        let p11 = load(buf, texels.index(x1, y1, c), sanitize);
+        let top = p00 + (p10 - p00) * fx;
+        let bottom = p01 + (p11 - p01) * fx;
+        *value = top + (bottom - top) * fy;
+    }
+    pixel
+}
+
+/// Bicubic Catmull-Rom sample of an image buffer at pixel-centre
+/// coordinates. All sixteen taps are clamped to the image border.
+#[allow(clippy::too_many_arguments)]
+fn sample_catmull_rom(
+    buf: &[f32],
+    texels: Texels,
+    w: usize,
+    h: usize,
+    channels: usize,
+    x: f32,
+    y: f32,
+    sanitize: bool,
+) -> [f32; 4] {
+    let x = x.clamp(0.0, (w - 1) as f32);
+    let y = y.clamp(0.0, (h - 1) as f32);
+    let x1 = x.floor();
+    let y1 = y.floor();
+    let wx = catmull_rom_weights(x - x1);
+    let wy = catmull_rom_weights(y - y1);
+    let tap = |base: f32, offset: isize, len: usize| -> usize {
+        (base as isize + offset).clamp(0, len as isize - 1) as usize
+    };
+
+    let mut pixel = [0.0_f32; 4];
+    for (j, weight_y) in wy.iter().enumerate() {
+        let ty = tap(y1, j as isize - 1, h);
+        for (i, weight_x) in wx.iter().enumerate() {
+            let tx = tap(x1, i as isize - 1, w);
+            let weight = weight_x * weight_y;
+            for (c, value) in pixel.iter_mut().enumerate().take(channels) {
+                *value += load(buf, texels.index(tx, ty, c), sanitize) * weight;
+            }
+        }
+    }
+    pixel
+}
+
+/// Catmull-Rom weights for the four taps around a sample at fraction `t`.
+fn catmull_rom_weights(t: f32) -> [f32; 4] {
+    let t2 = t * t;
+    let t3 = t2 * t;
+    [
+        -0.5 * t3 + t2 - 0.5 * t,
+        1.5 * t3 - 2.5 * t2 + 1.0,
+        -1.5 * t3 + 2.0 * t2 + 0.5 * t,
+        0.5 * t3 - 0.5 * t2,
+    ]
+}
+
+/// Per-channel min/max of the 3x3 neighbourhood around `(x, y)`.
+#[allow(clippy::too_many_arguments)]
+fn neighbourhood_range(
+    buf: &[f32],
+    texels: Texels,
+    w: usize,
+    h: usize,
+    channels: usize,
+    x: usize,
+    y: usize,
+    sanitize: bool,
+) -> ([f32; 4], [f32; 4]) {
+    let mut lo = [f32::INFINITY; 4];
+    let mut hi = [f32::NEG_INFINITY; 4];
+    for ny in y.saturating_sub(1)..=(y + 1).min(h - 1) {
+        for nx in x.saturating_sub(1)..=(x + 1).min(w - 1) {
+            for c in 0..channels {
+                let value = load(buf, texels.index(nx, ny, c), sanitize);
+                lo[c] = lo[c].min(value);
+                hi[c] = hi[c].max(value);
+            }
+        }
     }
+    (lo, hi)
 }
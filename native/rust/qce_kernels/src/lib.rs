@@ -1,4 +1,12 @@
 //! Core rendering kernels shared between WASM and Python bindings.
+//!
+//! The crate is `no_std` without the default `std` feature; enable `libm` for
+//! the float math and `alloc` for the kernels that need scratch buffers.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
 
 pub mod kernels {
     pub mod coherence;
@@ -6,8 +14,74 @@ pub mod kernels {
     pub mod taa;
 }
 
+pub mod error;
+pub mod kernel;
+mod math;
+#[cfg(all(feature = "rayon", not(target_arch = "wasm32")))]
+mod pool;
+#[cfg(feature = "alloc")]
+mod scratch;
 pub mod utils;
 
-pub use kernels::coherence::interference_field;
-pub use kernels::ssr::ssr_step;
-pub use kernels::taa::taa_reproject;
+pub use error::KernelError;
+pub use kernel::Kernel;
+
+pub use kernels::coherence::{
+    fill_interference_atlas, fill_interference_atlas_checked, fill_interference_field,
+    fill_interference_field_checked, interference_fbm, interference_field, interference_field_cfg,
+    interference_field_grad, interference_field_grad_cfg, interference_field_loop,
+    interference_field_seeded, interference_field_t64, interference_field_tiled,
+    interference_normal_map, interference_normal_map_checked, interference_remapped,
+    interference_rgb, interference_ridged, interference_turbulence, interference_warped,
+    InterferenceKernel, InterferenceParams, InterferenceWave,
+};
+pub use kernels::ssr::{
+    ggx_sample_direction, ssr_cone, ssr_contact_sharpness, ssr_edge_fade, ssr_march,
+    ssr_march_with, ssr_step, ssr_step_batch, ssr_step_batch_checked, ssr_step_fresnel,
+    ssr_step_with_budget, ssr_temporal_accumulate, ssr_temporal_accumulate_checked, SsrMarchParams,
+    SsrMarchResult, SSR_DEFAULT_MAX_STEPS,
+};
+pub use kernels::taa::{
+    taa_reproject, taa_reproject_checked, taa_reproject_debug, taa_reproject_per_channel,
+    taa_reproject_with, taa_reproject_with_checked, taa_upscale, HistorySampling, TaaFrame,
+    TaaKernel, TaaParams, TaaUpscaleFrame,
+};
+
+#[cfg(feature = "alloc")]
+pub use kernels::ssr::{build_hiz, build_hiz_with_scratch, ssr_march_hiz, HiZPyramid};
+#[cfg(all(feature = "half", feature = "alloc"))]
+pub use kernels::taa::taa_reproject_f16;
+#[cfg(feature = "alloc")]
+pub use kernels::taa::{
+    taa_reproject_in_place, taa_reproject_in_place_with_scratch, taa_reproject_planar,
+    taa_reproject_with_scratch, TaaContext,
+};
+#[cfg(all(feature = "rayon", not(target_arch = "wasm32")))]
+pub use pool::set_thread_pool_size;
+#[cfg(feature = "alloc")]
+pub use scratch::KernelScratch;
+
+/// Features this build was compiled with, in `Cargo.toml` order.
+#[cfg(feature = "alloc")]
+const ENABLED_FEATURES: &[(&str, bool)] = &[
+    ("std", cfg!(feature = "std")),
+    ("alloc", cfg!(feature = "alloc")),
+    ("libm", cfg!(feature = "libm")),
+    ("fast-math", cfg!(feature = "fast-math")),
+    ("rayon", cfg!(feature = "rayon")),
+    ("simd", cfg!(feature = "simd")),
+    ("half", cfg!(feature = "half")),
+];
+
+/// Crate version followed by the enabled features, e.g.
+/// `"0.1.0 (std,alloc,simd)"`, so binding consumers can pick fallbacks at
+/// runtime.
+#[cfg(feature = "alloc")]
+pub fn kernels_version() -> alloc::string::String {
+    let features: alloc::vec::Vec<&str> = ENABLED_FEATURES
+        .iter()
+        .filter(|(_, enabled)| *enabled)
+        .map(|(name, _)| *name)
+        .collect();
+    alloc::format!("{} ({})", env!("CARGO_PKG_VERSION"), features.join(","))
+}
@@ -0,0 +1,81 @@
+//! Float functions that `core` lacks. `std` builds use the inherent `f32`
+//! methods, which take precedence over this trait; `no_std` builds import
+//! [`F32Ext`] and get the same names backed by `libm`. Test builds always link
+//! `std`, so they skip the shim too.
+
+#[cfg(all(not(feature = "std"), not(feature = "libm")))]
+compile_error!("qce_kernels needs the `std` or the `libm` feature for float math");
+
+#[cfg(all(not(feature = "std"), feature = "libm", not(test)))]
+#[allow(dead_code)] // some methods are only used with `alloc`
+pub(crate) trait F32Ext {
+    fn sin(self) -> f32;
+    fn cos(self) -> f32;
+    fn sin_cos(self) -> (f32, f32);
+    fn exp(self) -> f32;
+    fn sqrt(self) -> f32;
+    fn powf(self, n: f32) -> f32;
+    fn powi(self, n: i32) -> f32;
+    fn floor(self) -> f32;
+    fn ceil(self) -> f32;
+    fn round(self) -> f32;
+    fn fract(self) -> f32;
+}
+
+#[cfg(all(not(feature = "std"), feature = "libm", not(test)))]
+impl F32Ext for f32 {
+    #[inline]
+    fn sin(self) -> f32 {
+        libm::sinf(self)
+    }
+
+    #[inline]
+    fn cos(self) -> f32 {
+        libm::cosf(self)
+    }
+
+    #[inline]
+    fn sin_cos(self) -> (f32, f32) {
+        libm::sincosf(self)
+    }
+
+    #[inline]
+    fn exp(self) -> f32 {
+        libm::expf(self)
+    }
+
+    #[inline]
+    fn sqrt(self) -> f32 {
+        libm::sqrtf(self)
+    }
+
+    #[inline]
+    fn powf(self, n: f32) -> f32 {
+        libm::powf(self, n)
+    }
+
+    #[inline]
+    fn powi(self, n: i32) -> f32 {
+        libm::powf(self, n as f32)
+    }
+
+    #[inline]
+    fn floor(self) -> f32 {
+        libm::floorf(self)
+    }
+
+    #[inline]
+    fn ceil(self) -> f32 {
+        libm::ceilf(self)
+    }
+
+    #[inline]
+    fn round(self) -> f32 {
+        libm::roundf(self)
+    }
+
+    #[inline]
+    fn fract(self) -> f32 {
+        self - libm::truncf(self)
+    }
+}
@@ -0,0 +1,38 @@
+//! Dedicated rayon pool for the parallel kernels.
+
+use std::sync::{Arc, RwLock};
+
+use rayon::{ThreadPool, ThreadPoolBuilder};
+
+use crate::error::KernelError;
+
+static POOL: RwLock<Option<Arc<ThreadPool>>> = RwLock::new(None);
+
+/// Runs the parallel kernels on a dedicated pool of `threads` workers instead
+/// of rayon's global pool, e.g. to leave cores free for a render thread.
+/// `0` drops the dedicated pool and goes back to the global one. Calls already
+/// running keep the pool they started on.
+pub fn set_thread_pool_size(threads: usize) -> Result<(), KernelError> {
+    let pool = if threads == 0 {
+        None
+    } else {
+        let pool = ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .thread_name(|i| format!("qce-kernels-{}", i))
+            .build()
+            .map_err(|_| KernelError::InvalidArgument("failed to build the rayon thread pool"))?;
+        Some(Arc::new(pool))
+    };
+    *POOL.write().unwrap_or_else(|err| err.into_inner()) = pool;
+    Ok(())
+}
+
+/// Runs `op` on the pool configured by [`set_thread_pool_size`], or directly
+/// (so on the global pool) when none is set.
+pub(crate) fn install<R: Send>(op: impl FnOnce() -> R + Send) -> R {
+    let pool = POOL.read().unwrap_or_else(|err| err.into_inner()).clone();
+    match pool {
+        Some(pool) => pool.install(op),
+        None => op(),
+    }
+}
@@ -0,0 +1,71 @@
+//! Reusable heap temporaries for the kernels that need scratch space.
+
+use alloc::vec::Vec;
+
+/// Pool of `f32` buffers that the heavier kernels borrow their temporaries
+/// from (the TAA sharpening rows and in-place ring, Hi-Z mip levels), so a
+/// pipeline that keeps one around stops allocating once it has warmed up.
+///
+/// Buffers grow to the largest size requested and are only released by
+/// [`clear`](Self::clear). Kernel results never depend on what the pool held
+/// before.
+#[derive(Clone, Debug, Default)]
+pub struct KernelScratch {
+    pool: Vec<Vec<f32>>,
+}
+
+impl KernelScratch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Releases every pooled buffer.
+    pub fn clear(&mut self) {
+        self.pool.clear();
+    }
+
+    /// Total capacity, in floats, currently held by the pool.
+    pub fn capacity(&self) -> usize {
+        self.pool.iter().map(Vec::capacity).sum()
+    }
+
+    /// A buffer of `len` copies of `value`. Reuses the smallest pooled buffer
+    /// that fits, or grows the largest one when none does.
+    pub(crate) fn take(&mut self, len: usize, value: f32) -> Vec<f32> {
+        let fits = self
+            .pool
+            .iter()
+            .enumerate()
+            .filter(|(_, buf)| buf.capacity() >= len)
+            .min_by_key(|(_, buf)| buf.capacity())
+            .map(|(i, _)| i);
+        let largest = || {
+            self.pool
+                .iter()
+                .enumerate()
+                .max_by_key(|(_, buf)| buf.capacity())
+                .map(|(i, _)| i)
+        };
+        let mut buf = match fits.or_else(largest) {
+            Some(i) => self.pool.swap_remove(i),
+            None => Vec::new(),
+        };
+        buf.clear();
+        buf.resize(len, value);
+        buf
+    }
+
+    /// A buffer holding a copy of `src`.
+    pub(crate) fn take_copy(&mut self, src: &[f32]) -> Vec<f32> {
+        let mut buf = self.take(src.len(), 0.0);
+        buf.copy_from_slice(src);
+        buf
+    }
+
+    /// Returns a buffer from [`take`](Self::take) to the pool.
+    pub(crate) fn give(&mut self, buf: Vec<f32>) {
+        if buf.capacity() > 0 {
+            self.pool.push(buf);
+        }
+    }
+}
@@ -1,4 +1,262 @@
+#[cfg(all(not(feature = "std"), not(test)))]
+use crate::math::F32Ext;
+
+use crate::error::{check_len, KernelError};
+
+pub mod rng;
+
+/// Clamps `x` to `[0, 1]`, mapping NaN to `0.0`.
 #[inline]
 pub fn clamp01(x: f32) -> f32 {
-    x.max(0.0).min(1.0)
+    if x.is_nan() {
+        0.0
+    } else {
+        x.clamp(0.0, 1.0)
+    }
+}
+
+/// Replaces NaN and ±Inf with `0.0`, passing finite values through.
+#[inline]
+pub fn sanitize(x: f32) -> f32 {
+    clamp_nan(x, 0.0)
+}
+
+/// Replaces NaN and ±Inf with `fallback`, passing finite values through.
+#[inline]
+pub fn clamp_nan(x: f32, fallback: f32) -> f32 {
+    if x.is_finite() {
+        x
+    } else {
+        fallback
+    }
+}
+
+/// Applies [`clamp_nan`] to every element of `values` in place.
+pub fn sanitize_slice(values: &mut [f32], fallback: f32) {
+    for v in values {
+        *v = clamp_nan(*v, fallback);
+    }
+}
+
+/// `true` when no element of `values` is NaN or infinite.
+pub fn is_all_finite(values: &[f32]) -> bool {
+    values.iter().all(|v| v.is_finite())
+}
+
+/// Reinhard tonemap `x / (1 + x)`, mapping `[0, inf)` into `[0, 1)`.
+#[inline]
+pub fn tonemap(x: f32) -> f32 {
+    let x = x.max(0.0);
+    x / (1.0 + x)
+}
+
+/// Inverse of [`tonemap`]. Inputs are clamped just below `1.0` so the result
+/// stays finite.
+#[inline]
+pub fn inverse_tonemap(y: f32) -> f32 {
+    let y = y.clamp(0.0, 1.0 - f32::EPSILON);
+    y / (1.0 - y)
+}
+
+/// Scales `x` about `0.5` by `contrast`, shifts it by `bias` and clamps the
+/// result to `[0, 1]`. `contrast = 1, bias = 0` only clamps. The result is in
+/// `[0, 1]` for every input: `x = 0.5` stays put even for infinite `contrast`,
+/// and a NaN input maps to `0.0`.
+#[inline]
+pub fn remap_contrast_bias(x: f32, contrast: f32, bias: f32) -> f32 {
+    let offset = x - 0.5;
+    let scaled = if offset == 0.0 {
+        0.0
+    } else {
+        offset * contrast
+    };
+    clamp01(scaled + 0.5 + bias)
+}
+
+/// Decodes an sRGB-encoded value to linear light using the piecewise sRGB
+/// curve (linear below `0.04045`, a 2.4 power above).
+#[inline]
+pub fn srgb_to_linear(x: f32) -> f32 {
+    if x <= 0.04045 {
+        x / 12.92
+    } else {
+        ((x + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Encodes a linear value with the sRGB curve; inverse of [`srgb_to_linear`].
+/// The knee sits at `0.0031308`.
+#[inline]
+pub fn linear_to_srgb(x: f32) -> f32 {
+    if x <= 0.003_130_8 {
+        x * 12.92
+    } else {
+        1.055 * x.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Applies [`srgb_to_linear`] to every element of `values` in place. Alpha is
+/// not special-cased, so skip it for RGBA data.
+pub fn srgb_to_linear_slice(values: &mut [f32]) {
+    for v in values {
+        *v = srgb_to_linear(*v);
+    }
+}
+
+/// Applies [`linear_to_srgb`] to every element of `values` in place.
+pub fn linear_to_srgb_slice(values: &mut [f32]) {
+    for v in values {
+        *v = linear_to_srgb(*v);
+    }
+}
+
+/// Copies a planar image (all of channel 0, then all of channel 1, ...) into
+/// interleaved pixels of `channels` floats. `planar` and `out` must have the
+/// same length, a multiple of `channels`.
+pub fn planar_to_interleaved(
+    planar: &[f32],
+    channels: usize,
+    out: &mut [f32],
+) -> Result<(), KernelError> {
+    let pixels = layout_pixels(planar.len(), channels)?;
+    check_len("interleaved", out.len(), planar.len())?;
+    for (c, plane) in planar.chunks_exact(pixels.max(1)).enumerate() {
+        for (i, &v) in plane.iter().enumerate() {
+            out[i * channels + c] = v;
+        }
+    }
+    Ok(())
+}
+
+/// Inverse of [`planar_to_interleaved`]: splits interleaved pixels of
+/// `channels` floats into one contiguous plane per channel.
+pub fn interleaved_to_planar(
+    interleaved: &[f32],
+    channels: usize,
+    out: &mut [f32],
+) -> Result<(), KernelError> {
+    let pixels = layout_pixels(interleaved.len(), channels)?;
+    check_len("planar", out.len(), interleaved.len())?;
+    for (i, pixel) in interleaved.chunks_exact(channels).enumerate() {
+        for (c, &v) in pixel.iter().enumerate() {
+            out[c * pixels + i] = v;
+        }
+    }
+    Ok(())
+}
+
+/// Expands RGB pixels to RGBA, writing `alpha` into every fourth channel.
+/// `src.len()` must be a multiple of 3 and `out` hold four floats per pixel.
+pub fn rgb_to_rgba(src: &[f32], alpha: f32, out: &mut [f32]) -> Result<(), KernelError> {
+    let pixels = layout_pixels(src.len(), 3)?;
+    check_len("rgba", out.len(), pixels * 4)?;
+    for (rgb, rgba) in src.chunks_exact(3).zip(out.chunks_exact_mut(4)) {
+        rgba[..3].copy_from_slice(rgb);
+        rgba[3] = alpha;
+    }
+    Ok(())
+}
+
+/// Drops the alpha channel of RGBA pixels. `src.len()` must be a multiple of
+/// 4 and `out` hold three floats per pixel.
+pub fn rgba_to_rgb(src: &[f32], out: &mut [f32]) -> Result<(), KernelError> {
+    let pixels = layout_pixels(src.len(), 4)?;
+    check_len("rgb", out.len(), pixels * 3)?;
+    for (rgba, rgb) in src.chunks_exact(4).zip(out.chunks_exact_mut(3)) {
+        rgb.copy_from_slice(&rgba[..3]);
+    }
+    Ok(())
+}
+
+/// Halves a `w * h` image of `channels` interleaved floats per pixel by
+/// averaging 2x2 blocks into `out`, which holds `w.div_ceil(2) *
+/// h.div_ceil(2)` pixels. With an odd dimension the last block clamps to the
+/// edge, so it averages the border pixels with themselves.
+pub fn downsample_2x(
+    src: &[f32],
+    w: usize,
+    h: usize,
+    channels: usize,
+    out: &mut [f32],
+) -> Result<(), KernelError> {
+    if channels == 0 {
+        return Err(KernelError::InvalidArgument(
+            "channel count must be positive",
+        ));
+    }
+    let len = w
+        .checked_mul(h)
+        .and_then(|pixels| pixels.checked_mul(channels))
+        .ok_or(KernelError::DimensionOverflow)?;
+    check_len("source", src.len(), len)?;
+    let (out_w, out_h) = (w.div_ceil(2), h.div_ceil(2));
+    check_len("output", out.len(), out_w * out_h * channels)?;
+
+    for y in 0..out_h {
+        let (y0, y1) = (2 * y, (2 * y + 1).min(h - 1));
+        for x in 0..out_w {
+            let (x0, x1) = (2 * x, (2 * x + 1).min(w - 1));
+            let dst = (y * out_w + x) * channels;
+            for c in 0..channels {
+                let tap = |tx: usize, ty: usize| src[(ty * w + tx) * channels + c];
+                out[dst + c] = (tap(x0, y0) + tap(x1, y0) + tap(x0, y1) + tap(x1, y1)) * 0.25;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Widens IEEE half-precision floats, given as raw `u16` bits (e.g. an
+/// RGBA16F readback), into `out`, which must have the same length.
+#[cfg(feature = "half")]
+pub fn f16_slice_to_f32(bits: &[u16], out: &mut [f32]) -> Result<(), KernelError> {
+    use half::slice::{HalfBitsSliceExt, HalfFloatSliceExt};
+
+    check_len("f32", out.len(), bits.len())?;
+    bits.reinterpret_cast::<half::f16>()
+        .convert_to_f32_slice(out);
+    Ok(())
+}
+
+/// Rounds `values` to the nearest half-precision float and writes the raw
+/// `u16` bits into `out`, which must have the same length.
+#[cfg(feature = "half")]
+pub fn f32_slice_to_f16(values: &[f32], out: &mut [u16]) -> Result<(), KernelError> {
+    use half::slice::{HalfBitsSliceExt, HalfFloatSliceExt};
+
+    check_len("f16", out.len(), values.len())?;
+    out.reinterpret_cast_mut::<half::f16>()
+        .convert_from_f32_slice(values);
+    Ok(())
+}
+
+/// Pixel count of a `len`-float buffer with `channels` floats per pixel.
+fn layout_pixels(len: usize, channels: usize) -> Result<usize, KernelError> {
+    if channels == 0 {
+        return Err(KernelError::InvalidArgument(
+            "channel count must be positive",
+        ));
+    }
+    if !len.is_multiple_of(channels) {
+        return Err(KernelError::InvalidArgument(
+            "buffer length must be a multiple of the channel count",
+        ));
+    }
+    Ok(len / channels)
+}
+
+/// Rec. 709 relative luminance of a linear RGB triple.
+#[inline]
+pub fn luminance(rgb: [f32; 3]) -> f32 {
+    rgb[0] * 0.2126 + rgb[1] * 0.7152 + rgb[2] * 0.0722
+}
+
+/// Interleaved gradient noise (Jimenez 2014) in `[0, 1)` for pixel `(x, y)`.
+/// Neighbouring pixels get well-spread values; `frame` scrolls the pattern so
+/// successive frames decorrelate under TAA.
+#[inline]
+pub fn interleaved_gradient_noise(x: u32, y: u32, frame: u32) -> f32 {
+    let x = x as f32 + 5.588238 * (frame % 64) as f32;
+    let y = y as f32 + 5.588238 * (frame % 64) as f32;
+    (52.982_918 * (0.067_110_56 * x + 0.005_837_15 * y).fract()).fract()
 }
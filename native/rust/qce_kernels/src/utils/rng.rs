@@ -0,0 +1,51 @@
+//! Small deterministic random number helpers shared by the seeded kernels.
+
+/// SplitMix64 generator: 64 bits of state, one add and a mix per draw. Not
+/// cryptographic, but the same seed always produces the same sequence on
+/// every target.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    /// Uniform float in `[0, 1)`, using the top 24 bits of the next draw.
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 * (1.0 / (1u32 << 24) as f32)
+    }
+}
+
+/// Small integer hash (lowbias32) with good avalanche, for turning seeds or
+/// coordinates into well-mixed bits.
+#[inline]
+pub fn hash_u32(mut x: u32) -> u32 {
+    x = x.wrapping_add(0x9e37_79b9);
+    x ^= x >> 16;
+    x = x.wrapping_mul(0x7feb_352d);
+    x ^= x >> 15;
+    x = x.wrapping_mul(0x846c_a68b);
+    x ^= x >> 16;
+    x
+}
+
+/// Hashes `x` with [`hash_u32`] to a float in `[0, 1)`.
+#[inline]
+pub fn hash_to_f32(x: u32) -> f32 {
+    (hash_u32(x) >> 8) as f32 * (1.0 / (1u32 << 24) as f32)
+}
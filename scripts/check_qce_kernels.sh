@@ -0,0 +1,25 @@
+#!/usr/bin/env bash
+# Build and lint the core qce_kernels crate across its supported feature sets,
+# including the no_std configurations.
+set -euo pipefail
+
+ROOT_DIR=$(cd "$(dirname "${BASH_SOURCE[0]}")/.." && pwd)
+cd "$ROOT_DIR/native/rust/qce_kernels"
+
+FEATURE_SETS=(
+  ""
+  "--no-default-features --features libm"
+  "--no-default-features --features alloc,libm"
+  "--no-default-features --features alloc,libm,simd"
+  "--features rayon,simd,half"
+)
+
+for features in "${FEATURE_SETS[@]}"; do
+  echo "==> qce_kernels ${features:-(default features)}"
+  # shellcheck disable=SC2086
+  cargo build ${features}
+  # shellcheck disable=SC2086
+  cargo clippy --all-targets ${features} -- -D warnings
+done
+
+cargo test